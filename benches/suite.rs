@@ -0,0 +1,19 @@
+// `cargo bench`, or `cargo bench -- 1000 100000` for other input sizes
+use algorithms::bench::{self, CountingAllocator, Group};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() {
+    // cargo passes --bench, and sometimes a filter
+    let mut sizes: Vec<usize> = std::env::args()
+        .skip(1)
+        .filter_map(|arg| arg.parse().ok())
+        .collect();
+    if sizes.is_empty() {
+        sizes = vec![1000, 10000, 100000];
+    }
+
+    let groups = [Group::Primes, Group::HashSets, Group::Search];
+    println!("{}", bench::table(&bench::run(&groups, &sizes, 10)));
+}
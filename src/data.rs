@@ -1,6 +1,13 @@
+// map_or(true, ..) stands in for Option::is_none_or, which needs Rust 1.82
+#[allow(clippy::module_inception, clippy::unnecessary_map_or)]
 pub mod data {
 
     // Regular ole stack, only using Vec a tiny bit
+    #[allow(
+        clippy::new_without_default,
+        clippy::assign_op_pattern,
+        unused_must_use
+    )]
     pub mod stack {
         #[derive(Debug)]
         pub struct Stack<T: Default> {
@@ -64,6 +71,7 @@ pub mod data {
         }
 
         #[cfg(test)]
+        #[allow(clippy::bool_assert_comparison)]
         mod tests {
             use super::*;
 
@@ -116,6 +124,12 @@ pub mod data {
     }
 
     // HashSet with open addressing and quadratic probing
+    #[allow(
+        clippy::new_without_default,
+        clippy::needless_return,
+        clippy::needless_borrow,
+        clippy::match_like_matches_macro
+    )]
     pub mod hash_set {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
@@ -247,6 +261,7 @@ pub mod data {
             }
         }
         #[cfg(test)]
+        #[allow(clippy::bool_assert_comparison)]
         mod tests {
             use super::*;
 
@@ -320,6 +335,7 @@ pub mod data {
     }
 
     // Way cleaner than Open Addressing - this is standard array + vector implementation
+    #[allow(clippy::new_without_default, clippy::needless_return)]
     pub mod hash_set2 {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
@@ -418,6 +434,7 @@ pub mod data {
         }
 
         #[cfg(test)]
+        #[allow(clippy::bool_assert_comparison)]
         mod tests {
             use super::*;
 
@@ -492,10 +509,77 @@ pub mod data {
 
     // Graph!
     pub mod graph {
-        use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+        use std::cmp::Reverse;
+        use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+        use std::error::Error;
+        use std::fmt;
+
+        pub type NodeLabel = usize;
+        pub type EdgeWeight = i32;
+
+        // A search's path, if it found one, and how many nodes it took off its frontier
+        #[derive(Debug, PartialEq)]
+        pub struct Search {
+            pub path: Option<Vec<NodeLabel>>,
+            pub expanded: usize,
+        }
+
+        // Dijkstra's answer can't be trusted once any edge weighs less than nothing
+        #[derive(Debug, PartialEq)]
+        pub struct NegativeWeight {
+            pub head: NodeLabel,
+            pub tail: NodeLabel,
+            pub weight: EdgeWeight,
+        }
+
+        impl fmt::Display for NegativeWeight {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    f,
+                    "edge {} -> {} has negative weight {}, which dijkstra can't handle",
+                    self.head, self.tail, self.weight
+                )
+            }
+        }
+
+        impl Error for NegativeWeight {}
+
+        // One step of a traced bfs or dfs. Visit is a node joining the visited set.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum TraceEvent<N = NodeLabel> {
+            Push(N),
+            Pop(N),
+            Visit(N),
+            Parent { node: N, parent: N },
+        }
+
+        impl<N> TraceEvent<N> {
+            // The same step with its nodes swapped for something else, like their names
+            pub fn map<M, F: FnMut(N) -> M>(self, mut f: F) -> TraceEvent<M> {
+                match self {
+                    TraceEvent::Push(node) => TraceEvent::Push(f(node)),
+                    TraceEvent::Pop(node) => TraceEvent::Pop(f(node)),
+                    TraceEvent::Visit(node) => TraceEvent::Visit(f(node)),
+                    TraceEvent::Parent { node, parent } => TraceEvent::Parent {
+                        node: f(node),
+                        parent: f(parent),
+                    },
+                }
+            }
+        }
 
-        type NodeLabel = usize;
-        type EdgeWeight = i32;
+        impl<N: fmt::Display> fmt::Display for TraceEvent<N> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    TraceEvent::Push(node) => write!(f, "push {}", node),
+                    TraceEvent::Pop(node) => write!(f, "pop {}", node),
+                    TraceEvent::Visit(node) => write!(f, "visit {}", node),
+                    TraceEvent::Parent { node, parent } => {
+                        write!(f, "parent {} <- {}", node, parent)
+                    }
+                }
+            }
+        }
 
         pub struct Graph<T> {
             nodes: Vec<T>,
@@ -507,40 +591,129 @@ pub mod data {
                 nodes: Vec<T>,
                 edge_list: Vec<(NodeLabel, NodeLabel, EdgeWeight)>,
             ) -> Graph<T> {
-                let mut edges = vec![BTreeMap::new(); nodes.len()];
-                for (head_id, tail_id, edge_weight) in edge_list.iter() {
-                    edges[*head_id].insert(*tail_id, *edge_weight);
+                let mut graph = Graph {
+                    edges: vec![BTreeMap::new(); nodes.len()],
+                    nodes,
+                };
+                for (head_id, tail_id, edge_weight) in edge_list {
+                    graph.add_edge(head_id, tail_id, edge_weight);
                 }
-
-                Graph { nodes, edges }
+                graph
             }
 
+            // Every edge weighs one, as in graph files without weights, so weighted
+            // algorithms count hops
             pub fn new_unweighted(
                 nodes: Vec<T>,
                 edge_list: Vec<(NodeLabel, NodeLabel)>,
             ) -> Graph<T> {
                 let edge_list = edge_list
                     .into_iter()
-                    .map(|(source, destination)| (source, destination, 0))
+                    .map(|(source, destination)| (source, destination, 1))
                     .collect();
 
                 Graph::new(nodes, edge_list)
             }
 
+            pub fn add_node(&mut self, payload: T) -> NodeLabel {
+                self.nodes.push(payload);
+                self.edges.push(BTreeMap::new());
+                self.nodes.len() - 1
+            }
+
+            // Replaces the weight if the edge is already there. Both ends must be nodes.
+            pub fn add_edge(
+                &mut self,
+                head_id: NodeLabel,
+                tail_id: NodeLabel,
+                edge_weight: EdgeWeight,
+            ) {
+                for node in [head_id, tail_id].iter() {
+                    assert!(
+                        *node < self.nodes.len(),
+                        "node {} is out of range for {} nodes",
+                        node,
+                        self.nodes.len()
+                    );
+                }
+                self.edges[head_id].insert(tail_id, edge_weight);
+            }
+
+            // None if there is no such edge, including when head_id isn't a node
+            pub fn remove_edge(
+                &mut self,
+                head_id: NodeLabel,
+                tail_id: NodeLabel,
+            ) -> Option<EdgeWeight> {
+                self.edges.get_mut(head_id)?.remove(&tail_id)
+            }
+
+            pub fn num_nodes(&self) -> usize {
+                self.nodes.len()
+            }
+
+            pub fn num_edges(&self) -> usize {
+                self.edges.iter().map(|edges| edges.len()).sum()
+            }
+
+            // The same edges with new node payloads
+            pub fn map_nodes<U, F: FnMut(&T) -> U>(&self, f: F) -> Graph<U> {
+                Graph {
+                    nodes: self.nodes.iter().map(f).collect(),
+                    edges: self.edges.clone(),
+                }
+            }
+
+            // Every edge as (source, target, weight), ordered by source then target
+            pub fn edge_list(&self) -> Vec<(NodeLabel, NodeLabel, EdgeWeight)> {
+                self.edges
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(node, edges)| {
+                        edges
+                            .iter()
+                            .map(move |(edge_node, edge_weight)| (node, *edge_node, *edge_weight))
+                    })
+                    .collect()
+            }
+
             pub fn dfs(
                 &self,
                 source_id: NodeLabel,
                 destination_id: NodeLabel,
             ) -> Option<Vec<NodeLabel>> {
+                self.dfs_search(source_id, destination_id).path
+            }
+
+            pub fn dfs_search(&self, source_id: NodeLabel, destination_id: NodeLabel) -> Search {
+                self.dfs_traced(source_id, destination_id, |_event| {})
+            }
+
+            // dfs_search, calling trace at every step
+            pub fn dfs_traced<F: FnMut(TraceEvent)>(
+                &self,
+                source_id: NodeLabel,
+                destination_id: NodeLabel,
+                mut trace: F,
+            ) -> Search {
                 let mut parents = HashMap::new();
                 let mut expanded = HashSet::new();
+                let mut num_expanded = 0;
 
                 let mut stack = vec![source_id];
+                trace(TraceEvent::Push(source_id));
 
                 while let Some(node) = stack.pop() {
-                    expanded.insert(node);
+                    trace(TraceEvent::Pop(node));
+                    if expanded.insert(node) {
+                        trace(TraceEvent::Visit(node));
+                    }
+                    num_expanded += 1;
                     if node == destination_id {
-                        return Some(self.backtrace(node, &parents));
+                        return Search {
+                            path: Some(self.backtrace(node, &parents)),
+                            expanded: num_expanded,
+                        };
                     }
 
                     // Reverse edges here because it pleases me to have DFS go down the
@@ -548,11 +721,19 @@ pub mod data {
                     for (edge, _edge_weight) in self.edges[node].iter().rev() {
                         if !expanded.contains(edge) {
                             stack.push(*edge);
+                            trace(TraceEvent::Push(*edge));
                             parents.insert(*edge, node);
+                            trace(TraceEvent::Parent {
+                                node: *edge,
+                                parent: node,
+                            });
                         }
                     }
                 }
-                None
+                Search {
+                    path: None,
+                    expanded: num_expanded,
+                }
             }
 
             pub fn bfs(
@@ -560,27 +741,234 @@ pub mod data {
                 source_id: NodeLabel,
                 destination_id: NodeLabel,
             ) -> Option<Vec<NodeLabel>> {
+                self.bfs_search(source_id, destination_id).path
+            }
+
+            pub fn bfs_search(&self, source_id: NodeLabel, destination_id: NodeLabel) -> Search {
+                self.bfs_traced(source_id, destination_id, |_event| {})
+            }
+
+            // bfs_search, calling trace at every step
+            pub fn bfs_traced<F: FnMut(TraceEvent)>(
+                &self,
+                source_id: NodeLabel,
+                destination_id: NodeLabel,
+                mut trace: F,
+            ) -> Search {
                 let mut queue = VecDeque::new();
                 let mut parents = HashMap::new();
                 let mut expanded = HashSet::new();
+                let mut num_expanded = 0;
 
                 expanded.insert(source_id);
+                trace(TraceEvent::Visit(source_id));
                 queue.push_back(source_id);
+                trace(TraceEvent::Push(source_id));
 
                 while let Some(node) = queue.pop_front() {
+                    trace(TraceEvent::Pop(node));
+                    num_expanded += 1;
                     if node == destination_id {
-                        return Some(self.backtrace(node, &parents));
+                        return Search {
+                            path: Some(self.backtrace(node, &parents)),
+                            expanded: num_expanded,
+                        };
                     }
 
                     for (edge_node, _edge_weight) in self.edges[node].iter() {
-                        if !expanded.contains(&edge_node) {
+                        if !expanded.contains(edge_node) {
                             parents.insert(*edge_node, node);
+                            trace(TraceEvent::Parent {
+                                node: *edge_node,
+                                parent: node,
+                            });
                             expanded.insert(*edge_node);
+                            trace(TraceEvent::Visit(*edge_node));
+                            queue.push_back(*edge_node);
+                            trace(TraceEvent::Push(*edge_node));
+                        }
+                    }
+                }
+                Search {
+                    path: None,
+                    expanded: num_expanded,
+                }
+            }
+
+            // Cheapest path by total weight. Graphs with a negative weight anywhere are
+            // refused up front rather than given a wrong answer.
+            pub fn dijkstra(
+                &self,
+                source_id: NodeLabel,
+                destination_id: NodeLabel,
+            ) -> Result<Option<Vec<NodeLabel>>, NegativeWeight> {
+                Ok(self.dijkstra_search(source_id, destination_id)?.path)
+            }
+
+            pub fn dijkstra_search(
+                &self,
+                source_id: NodeLabel,
+                destination_id: NodeLabel,
+            ) -> Result<Search, NegativeWeight> {
+                let negative = self.edges.iter().enumerate().find_map(|(head, edges)| {
+                    edges
+                        .iter()
+                        .find(|(_tail, weight)| **weight < 0)
+                        .map(|(tail, weight)| NegativeWeight {
+                            head,
+                            tail: *tail,
+                            weight: *weight,
+                        })
+                });
+                if let Some(negative) = negative {
+                    return Err(negative);
+                }
+
+                let mut distances: Vec<Option<i64>> = vec![None; self.nodes.len()];
+                let mut settled = vec![false; self.nodes.len()];
+                let mut parents = HashMap::new();
+                let mut heap = BinaryHeap::new();
+                let mut num_expanded = 0;
+
+                distances[source_id] = Some(0);
+                heap.push(Reverse((0, source_id)));
+
+                while let Some(Reverse((distance, node))) = heap.pop() {
+                    if settled[node] {
+                        continue;
+                    }
+                    settled[node] = true;
+                    num_expanded += 1;
+                    if node == destination_id {
+                        return Ok(Search {
+                            path: Some(self.backtrace(node, &parents)),
+                            expanded: num_expanded,
+                        });
+                    }
+
+                    for (edge_node, edge_weight) in self.edges[node].iter() {
+                        let edge_distance = distance + i64::from(*edge_weight);
+                        if !settled[*edge_node]
+                            && distances[*edge_node].map_or(true, |known| edge_distance < known)
+                        {
+                            distances[*edge_node] = Some(edge_distance);
+                            parents.insert(*edge_node, node);
+                            heap.push(Reverse((edge_distance, *edge_node)));
+                        }
+                    }
+                }
+                Ok(Search {
+                    path: None,
+                    expanded: num_expanded,
+                })
+            }
+
+            // Total weight along a path, None if a step isn't an edge
+            pub fn path_cost(&self, path: &[NodeLabel]) -> Option<i64> {
+                path.windows(2)
+                    .map(|pair| self.edges[pair[0]].get(&pair[1]).map(|w| i64::from(*w)))
+                    .sum()
+            }
+
+            // Edges in both directions, without self loops. When both directions are
+            // present with different weights, the smaller weight wins.
+            fn undirected_edges(&self) -> Vec<BTreeMap<NodeLabel, EdgeWeight>> {
+                let mut undirected = vec![BTreeMap::new(); self.nodes.len()];
+                for (node, edges) in self.edges.iter().enumerate() {
+                    for (edge_node, edge_weight) in edges.iter() {
+                        if node == *edge_node {
+                            continue;
+                        }
+                        for (from, to) in [(node, *edge_node), (*edge_node, node)].iter() {
+                            let weight = undirected[*from].entry(*to).or_insert(*edge_weight);
+                            if *edge_weight < *weight {
+                                *weight = *edge_weight;
+                            }
+                        }
+                    }
+                }
+                undirected
+            }
+
+            // No cycles when edge direction is ignored. As in RootedTree::from_graph, edges
+            // both ways between two nodes are one undirected edge; self loops are cycles.
+            pub fn is_forest(&self) -> bool {
+                self.forest_edges().is_some()
+            }
+
+            // A connected forest: n - 1 undirected edges and no cycles
+            pub fn is_tree(&self) -> bool {
+                !self.nodes.is_empty() && self.forest_edges() == Some(self.nodes.len() - 1)
+            }
+
+            // Exactly one directed path from root to every node
+            pub fn is_arborescence(&self, root: NodeLabel) -> bool {
+                if root >= self.nodes.len() {
+                    return false;
+                }
+                let in_degrees = self.in_degrees();
+                let single_parent = in_degrees
+                    .iter()
+                    .enumerate()
+                    .all(|(node, degree)| *degree == if node == root { 0 } else { 1 });
+                single_parent && self.hop_distances(root).iter().all(|d| d.is_some())
+            }
+
+            // The root of the graph if it is an arborescence
+            pub fn find_root(&self) -> Option<NodeLabel> {
+                let mut sources = self
+                    .in_degrees()
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_node, degree)| *degree == 0)
+                    .map(|(node, _degree)| node);
+                match (sources.next(), sources.next()) {
+                    (Some(root), None) if self.is_arborescence(root) => Some(root),
+                    _ => None,
+                }
+            }
+
+            // The number of undirected edges, or None if they contain a cycle
+            fn forest_edges(&self) -> Option<usize> {
+                if (0..self.nodes.len()).any(|node| self.edges[node].contains_key(&node)) {
+                    return None;
+                }
+                let mut components: Vec<NodeLabel> = (0..self.nodes.len()).collect();
+                let mut num_edges = 0;
+                for (node, edges) in self.undirected_edges().iter().enumerate() {
+                    for edge_node in edges.keys().filter(|edge_node| **edge_node > node) {
+                        let (a, b) = (
+                            find(&mut components, node),
+                            find(&mut components, *edge_node),
+                        );
+                        if a == b {
+                            return None;
+                        }
+                        components[a] = b;
+                        num_edges += 1;
+                    }
+                }
+                Some(num_edges)
+            }
+
+            // Number of edges on the shortest path from source to every node, if reachable
+            fn hop_distances(&self, source_id: NodeLabel) -> Vec<Option<usize>> {
+                let mut distances = vec![None; self.nodes.len()];
+                let mut queue = VecDeque::new();
+
+                distances[source_id] = Some(0);
+                queue.push_back(source_id);
+
+                while let Some(node) = queue.pop_front() {
+                    let distance = distances[node].unwrap_or(0);
+                    for edge_node in self.edges[node].keys() {
+                        if distances[*edge_node].is_none() {
+                            distances[*edge_node] = Some(distance + 1);
                             queue.push_back(*edge_node);
                         }
                     }
                 }
-                None
+                distances
             }
 
             fn backtrace(&self, node: usize, parents: &HashMap<usize, usize>) -> Vec<usize> {
@@ -594,6 +982,15 @@ pub mod data {
             }
         }
 
+        // Union-find lookup with path halving
+        fn find(components: &mut [NodeLabel], mut node: NodeLabel) -> NodeLabel {
+            while components[node] != node {
+                components[node] = components[components[node]];
+                node = components[node];
+            }
+            node
+        }
+
         #[cfg(test)]
         mod tests {
             use super::*;
@@ -663,123 +1060,6939 @@ pub mod data {
                 assert_eq!(Some(vec!(0, 1, 2, 3)), graph.dfs(0, 3));
                 assert_eq!(None, graph.dfs(1, 0));
             }
-        }
-    }
 
-    // Trie!
-    pub mod trie {
+            #[test]
+            fn test_edit_graph() {
+                let mut graph = Graph::new_unweighted(vec![0; 2], vec![(0, 1)]);
+                let node = graph.add_node(0);
+                graph.add_edge(1, node, 4);
+                assert_eq!(graph.dijkstra(0, 2), Ok(Some(vec![0, 1, 2])));
+                assert_eq!(graph.remove_edge(1, 2), Some(4));
+                assert_eq!(graph.remove_edge(1, 2), None);
+                assert_eq!(graph.remove_edge(7, 0), None);
+                assert_eq!(graph.bfs(0, 2), None);
+            }
 
-        use std::str::Chars;
+            #[test]
+            #[should_panic(expected = "node 3 is out of range for 3 nodes")]
+            fn test_add_edge_out_of_range() {
+                let mut graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1)]);
+                graph.add_edge(0, 3, 1);
+            }
 
-        #[derive(Debug)]
-        struct Trie {
-            value: Option<char>,
-            children: Vec<Trie>,
-        }
+            #[test]
+            fn test_dijkstra_negative_weight() {
+                let graph = Graph::new(
+                    vec![0; 4],
+                    vec![(0, 1, 1), (1, 2, -3), (0, 2, 1), (3, 0, 1)],
+                );
+                let error = NegativeWeight {
+                    head: 1,
+                    tail: 2,
+                    weight: -3,
+                };
+                // Refused whether or not the search would reach the negative edge
+                assert_eq!(graph.dijkstra(0, 2), Err(error));
+                assert!(graph.dijkstra(0, 1).is_err());
+                assert!(graph.dijkstra(3, 0).is_err());
+                assert_eq!(
+                    graph.dijkstra_search(0, 2).err().unwrap().to_string(),
+                    "edge 1 -> 2 has negative weight -3, which dijkstra can't handle"
+                );
+            }
 
-        impl Trie {
-            pub fn new() -> Trie {
-                Trie {
-                    value: None,
-                    children: Vec::new(),
-                }
+            #[test]
+            fn test_search_stats() {
+                let graph =
+                    Graph::new(vec![0; 4], vec![(0, 1, 2), (0, 2, 1), (1, 3, 5), (2, 3, 1)]);
+
+                let search = graph.bfs_search(0, 3);
+                assert_eq!(search.path, Some(vec![0, 1, 3]));
+                assert_eq!(search.expanded, 4);
+                assert_eq!(graph.dfs_search(0, 2).expanded, 4);
+                assert_eq!(graph.dfs_search(3, 0).expanded, 1);
+
+                let search = graph.dijkstra_search(0, 3).unwrap();
+                assert_eq!(search.path, Some(vec![0, 2, 3]));
+                assert_eq!(search.expanded, 4);
+
+                assert_eq!(graph.path_cost(&[0, 1, 3]), Some(7));
+                assert_eq!(graph.path_cost(&[0]), Some(0));
+                assert_eq!(graph.path_cost(&[0, 3]), None);
             }
 
-            pub fn contains(&self, t: &str) -> bool {
-                if let Some(current) = self.child_matches(t) {
-                    current.children.iter().any(|child| child.value == None)
-                } else {
-                    false
-                }
+            #[test]
+            fn test_is_tree_is_forest() {
+                // Branching trees, with edges pointing either way
+                let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 1), (1, 2), (0, 3)]);
+                assert!(graph.is_tree());
+                assert!(graph.is_forest());
+                let graph = Graph::new_unweighted(vec![0; 4], vec![(1, 0), (2, 1), (0, 3)]);
+                assert!(graph.is_tree());
+
+                // Edges both ways are one undirected edge
+                let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 0), (1, 2), (2, 1)]);
+                assert!(graph.is_tree());
+                let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 1), (1, 0), (2, 3)]);
+                assert!(!graph.is_tree());
+                assert!(graph.is_forest());
+
+                let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 2), (2, 0)]);
+                assert!(!graph.is_forest());
+                let graph = Graph::new_unweighted(vec![0; 2], vec![(0, 1), (1, 1)]);
+                assert!(!graph.is_forest());
+
+                let graph = Graph::new_unweighted(vec![0; 5], vec![(0, 1), (0, 2), (3, 4)]);
+                assert!(!graph.is_tree());
+                assert!(graph.is_forest());
+
+                let graph: Graph<i32> = Graph::new(vec![], vec![]);
+                assert!(!graph.is_tree());
+                assert!(graph.is_forest());
             }
 
-            pub fn contains_prefix(&self, t: &str) -> bool {
-                self.child_matches(t).is_some()
+            #[test]
+            fn test_is_arborescence_find_root() {
+                let graph = Graph::new_unweighted(vec![0; 5], vec![(2, 0), (2, 1), (0, 3), (0, 4)]);
+                assert!(graph.is_arborescence(2));
+                assert!(!graph.is_arborescence(0));
+                assert_eq!(graph.find_root(), Some(2));
+
+                // A tree, but 1 has two parents
+                let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (2, 1)]);
+                assert!(graph.is_tree());
+                assert_eq!(graph.find_root(), None);
+
+                // One parent each, but 1 and 2 form a loop the root can't reach
+                let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 3), (1, 2), (2, 1)]);
+                assert!(!graph.is_arborescence(0));
+                assert_eq!(graph.find_root(), None);
             }
 
-            fn child_matches(&self, t: &str) -> Option<&Trie> {
-                let mut current = self;
-                for c in t.chars() {
-                    let position = current
-                        .children
+            #[test]
+            fn test_trace() {
+                let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 1), (0, 2), (1, 3), (2, 3)]);
+                let pops = |events: &[TraceEvent]| -> Vec<NodeLabel> {
+                    events
                         .iter()
-                        .position(|child| child.value == Some(c));
-                    if let Some(index) = position {
-                        current = &current.children[index];
-                    } else {
-                        return None;
+                        .filter_map(|event| match event {
+                            TraceEvent::Pop(node) => Some(*node),
+                            _ => None,
+                        })
+                        .collect()
+                };
+
+                let mut bfs_events = Vec::new();
+                let search = graph.bfs_traced(0, 3, |event| bfs_events.push(event));
+                assert_eq!(search, graph.bfs_search(0, 3));
+                assert_eq!(pops(&bfs_events), vec![0, 1, 2, 3]);
+                assert_eq!(bfs_events[..2], [TraceEvent::Visit(0), TraceEvent::Push(0)]);
+
+                let mut dfs_events = Vec::new();
+                graph.dfs_traced(0, 3, |event| dfs_events.push(event));
+                assert_eq!(pops(&dfs_events), vec![0, 1, 3]);
+                assert_eq!(
+                    dfs_events.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+                    vec![
+                        "push 0",
+                        "pop 0",
+                        "visit 0",
+                        "push 2",
+                        "parent 2 <- 0",
+                        "push 1",
+                        "parent 1 <- 0",
+                        "pop 1",
+                        "visit 1",
+                        "push 3",
+                        "parent 3 <- 1",
+                        "pop 3",
+                        "visit 3",
+                    ]
+                );
+
+                let names = ["a", "b", "c", "d"];
+                let named = dfs_events[4].map(|node| names[node]);
+                assert_eq!(
+                    named,
+                    TraceEvent::Parent {
+                        node: "c",
+                        parent: "a"
                     }
-                }
-                Some(current)
+                );
+                assert_eq!(named.to_string(), "parent c <- a");
             }
+        }
 
-            pub fn add(&mut self, values: &str) {
-                let mut chars = values.chars();
-                self.add_chars(&mut chars);
-            }
+        // Which nodes matter most? Scores are indexed by NodeLabel
+        pub mod centrality {
+            use super::{Graph, NodeLabel};
+            use std::cmp::Reverse;
+            use std::collections::{BinaryHeap, VecDeque};
+
+            impl<T> Graph<T> {
+                // Power iteration until the total change in rank drops below tolerance.
+                // Dangling nodes (no out edges) spread their rank evenly over every node,
+                // so the ranks always sum to one.
+                pub fn pagerank(
+                    &self,
+                    damping: f64,
+                    tolerance: f64,
+                    max_iterations: usize,
+                ) -> Vec<f64> {
+                    let num_nodes = self.nodes.len();
+                    if num_nodes == 0 {
+                        return Vec::new();
+                    }
+                    let n = num_nodes as f64;
+
+                    let mut ranks = vec![1.0 / n; num_nodes];
+                    for _ in 0..max_iterations {
+                        let dangling: f64 = self
+                            .edges
+                            .iter()
+                            .zip(ranks.iter())
+                            .filter(|(edges, _rank)| edges.is_empty())
+                            .map(|(_edges, rank)| rank)
+                            .sum();
+
+                        let base = (1.0 - damping) / n + damping * dangling / n;
+                        let mut next_ranks = vec![base; num_nodes];
+
+                        for (node, edges) in self.edges.iter().enumerate() {
+                            if edges.is_empty() {
+                                continue;
+                            }
+                            let share = damping * ranks[node] / edges.len() as f64;
+                            for edge_node in edges.keys() {
+                                next_ranks[*edge_node] += share;
+                            }
+                        }
 
-            fn add_chars(&mut self, values: &mut Chars) {
-                let mut current = self;
-                for c in values {
-                    let child_index = current.add_char(c);
-                    current = &mut current.children[child_index];
+                        let change: f64 = ranks
+                            .iter()
+                            .zip(next_ranks.iter())
+                            .map(|(old, new)| (old - new).abs())
+                            .sum();
+                        ranks = next_ranks;
+
+                        if change < tolerance {
+                            break;
+                        }
+                    }
+                    ranks
                 }
 
-                current.set_complete();
-            }
+                // Brandes' algorithm counting every edge as length one.
+                // Pairs are ordered, so an undirected graph (edges both ways) counts each pair twice.
+                pub fn betweenness_centrality(&self) -> Vec<f64> {
+                    let num_nodes = self.nodes.len();
+                    let mut centrality = vec![0.0; num_nodes];
+
+                    for source_id in 0..num_nodes {
+                        let mut order = Vec::new();
+                        let mut predecessors = vec![Vec::new(); num_nodes];
+                        let mut paths = vec![0.0; num_nodes];
+                        let mut distances = vec![None; num_nodes];
+                        let mut queue = VecDeque::new();
+
+                        paths[source_id] = 1.0;
+                        distances[source_id] = Some(0);
+                        queue.push_back(source_id);
+
+                        while let Some(node) = queue.pop_front() {
+                            order.push(node);
+                            let distance = distances[node].unwrap_or(0);
+
+                            for edge_node in self.edges[node].keys() {
+                                if distances[*edge_node].is_none() {
+                                    distances[*edge_node] = Some(distance + 1);
+                                    queue.push_back(*edge_node);
+                                }
+                                if distances[*edge_node] == Some(distance + 1) {
+                                    paths[*edge_node] += paths[node];
+                                    predecessors[*edge_node].push(node);
+                                }
+                            }
+                        }
 
-            fn add_char(&mut self, value: char) -> usize {
-                let position = self
-                    .children
-                    .iter()
-                    .position(|child| child.value == Some(value));
+                        accumulate(source_id, &order, &predecessors, &paths, &mut centrality);
+                    }
+                    centrality
+                }
 
-                if let Some(index) = position {
-                    return index;
+                // Brandes' algorithm on top of Dijkstra. Edge weights should be positive:
+                // zero-weight edges can hide some of the tied shortest paths.
+                pub fn weighted_betweenness_centrality(&self) -> Vec<f64> {
+                    let num_nodes = self.nodes.len();
+                    let mut centrality = vec![0.0; num_nodes];
+
+                    for source_id in 0..num_nodes {
+                        let mut order = Vec::new();
+                        let mut predecessors = vec![Vec::new(); num_nodes];
+                        let mut paths = vec![0.0; num_nodes];
+                        let mut distances: Vec<Option<i64>> = vec![None; num_nodes];
+                        let mut settled = vec![false; num_nodes];
+                        let mut heap = BinaryHeap::new();
+
+                        paths[source_id] = 1.0;
+                        distances[source_id] = Some(0);
+                        heap.push(Reverse((0, source_id)));
+
+                        while let Some(Reverse((distance, node))) = heap.pop() {
+                            if settled[node] {
+                                continue;
+                            }
+                            settled[node] = true;
+                            order.push(node);
+
+                            for (edge_node, edge_weight) in self.edges[node].iter() {
+                                if settled[*edge_node] {
+                                    continue;
+                                }
+                                let candidate = distance + i64::from(*edge_weight);
+                                match distances[*edge_node] {
+                                    Some(current) if candidate > current => {}
+                                    Some(current) if candidate == current => {
+                                        paths[*edge_node] += paths[node];
+                                        predecessors[*edge_node].push(node);
+                                    }
+                                    _ => {
+                                        distances[*edge_node] = Some(candidate);
+                                        paths[*edge_node] = paths[node];
+                                        predecessors[*edge_node] = vec![node];
+                                        heap.push(Reverse((candidate, *edge_node)));
+                                    }
+                                }
+                            }
+                        }
+
+                        accumulate(source_id, &order, &predecessors, &paths, &mut centrality);
+                    }
+                    centrality
+                }
+
+                // Wasserman and Faust closeness over outgoing hop distances, which stays
+                // meaningful when not every node is reachable. Nodes that reach nothing score 0.
+                pub fn closeness_centrality(&self) -> Vec<f64> {
+                    let num_nodes = self.nodes.len();
+                    (0..num_nodes)
+                        .map(|node| {
+                            let distances = self.hop_distances(node);
+                            let reachable = distances.iter().filter(|d| d.is_some()).count() - 1;
+                            let total: usize = distances.iter().filter_map(|d| *d).sum();
+                            if total == 0 {
+                                return 0.0;
+                            }
+                            let reachable = reachable as f64;
+                            (reachable / total as f64) * (reachable / (num_nodes - 1) as f64)
+                        })
+                        .collect()
+                }
+
+                // Sum of 1 / distance to every other reachable node
+                pub fn harmonic_centrality(&self) -> Vec<f64> {
+                    (0..self.nodes.len())
+                        .map(|node| {
+                            self.hop_distances(node)
+                                .into_iter()
+                                .flatten()
+                                .filter(|&d| d > 0)
+                                .map(|d| 1.0 / d as f64)
+                                .sum()
+                        })
+                        .collect()
                 }
-                self.children.push(Trie {
-                    value: Some(value),
-                    children: Vec::new(),
-                });
-                self.children.len() - 1
             }
 
-            fn set_complete(&mut self) {
-                for child in self.children.iter() {
-                    if child.value == None {
-                        return;
+            // Walk back from the furthest nodes, handing each node's dependency to its predecessors
+            fn accumulate(
+                source_id: NodeLabel,
+                order: &[NodeLabel],
+                predecessors: &[Vec<NodeLabel>],
+                paths: &[f64],
+                centrality: &mut [f64],
+            ) {
+                let mut dependency = vec![0.0; centrality.len()];
+                for node in order.iter().rev() {
+                    for predecessor in predecessors[*node].iter() {
+                        dependency[*predecessor] +=
+                            paths[*predecessor] / paths[*node] * (1.0 + dependency[*node]);
+                    }
+                    if *node != source_id {
+                        centrality[*node] += dependency[*node];
                     }
                 }
-
-                self.children.push(Trie {
-                    value: None,
-                    children: Vec::new(),
-                });
             }
-        }
 
-        #[cfg(test)]
-        mod tests {
-            use super::*;
+            #[cfg(test)]
+            mod tests {
+                use super::*;
 
-            #[test]
-            fn test_contains() {
-                let mut trie = Trie::new();
+                fn assert_close(actual: &[f64], expected: &[f64]) {
+                    assert_eq!(actual.len(), expected.len());
+                    for (a, e) in actual.iter().zip(expected.iter()) {
+                        assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+                    }
+                }
 
-                assert!(!trie.contains("a"));
+                #[test]
+                fn test_pagerank() {
+                    let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 2), (2, 0)]);
+                    let ranks = graph.pagerank(0.85, 1e-10, 100);
+                    assert_close(&ranks, &[1.0 / 3.0; 3]);
+
+                    // Node 3 is dangling, but rank is not lost
+                    let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 3), (1, 3), (2, 3)]);
+                    let ranks = graph.pagerank(0.85, 1e-10, 100);
+                    assert!((ranks.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+                    assert!(ranks[3] > ranks[0]);
+                    assert_close(&ranks[0..3], &[ranks[0]; 3]);
+
+                    let graph: Graph<i32> = Graph::new(vec![], vec![]);
+                    assert!(graph.pagerank(0.85, 1e-10, 100).is_empty());
+                }
 
-                trie.add("abc");
-                assert!(trie.contains("abc"));
+                #[test]
+                fn test_betweenness_centrality() {
+                    let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 2)]);
+                    assert_close(&graph.betweenness_centrality(), &[0.0, 1.0, 0.0]);
 
-                assert!(!trie.contains("ab"));
-                assert!(!trie.contains("bca"));
-                assert!(!trie.contains("abcd"));
+                    // Two equally short routes from 0 to 3 split the credit
+                    let graph =
+                        Graph::new_unweighted(vec![0; 4], vec![(0, 1), (0, 2), (1, 3), (2, 3)]);
+                    assert_close(&graph.betweenness_centrality(), &[0.0, 0.5, 0.5, 0.0]);
+                }
 
-                assert!(trie.contains_prefix("ab"));
-                assert!(!trie.contains_prefix("bca"));
+                #[test]
+                fn test_weighted_betweenness_centrality() {
+                    let graph = Graph::new(vec![0; 3], vec![(0, 1, 1), (1, 2, 1), (0, 2, 5)]);
+                    assert_close(&graph.weighted_betweenness_centrality(), &[0.0, 1.0, 0.0]);
+                    assert_close(&graph.betweenness_centrality(), &[0.0, 0.0, 0.0]);
 
-                trie.add("abcde");
-                assert!(trie.contains("abcde"));            
+                    let graph = Graph::new(vec![0; 3], vec![(0, 1, 1), (1, 2, 1), (0, 2, 2)]);
+                    assert_close(&graph.weighted_betweenness_centrality(), &[0.0, 0.5, 0.0]);
+                }
+
+                #[test]
+                fn test_weighted_centrality_unweighted_graph() {
+                    // Unweighted edges weigh one, so weighted shortest paths count hops
+                    let graph =
+                        Graph::new_unweighted(vec![0; 4], vec![(0, 1), (0, 2), (1, 3), (2, 3)]);
+                    assert_close(
+                        &graph.weighted_betweenness_centrality(),
+                        &graph.betweenness_centrality(),
+                    );
+                    assert_close(
+                        &graph.weighted_betweenness_centrality(),
+                        &[0.0, 0.5, 0.5, 0.0],
+                    );
+                }
+
+                #[test]
+                fn test_closeness_harmonic_centrality() {
+                    let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 2)]);
+                    assert_close(&graph.closeness_centrality(), &[2.0 / 3.0, 0.5, 0.0]);
+                    assert_close(&graph.harmonic_centrality(), &[1.5, 1.0, 0.0]);
+                }
+            }
+        }
+
+        // Summary numbers for sanity-checking a graph before searching it
+        pub mod stats {
+            use super::{Graph, NodeLabel};
+            use std::collections::{BTreeMap, VecDeque};
+            use std::fmt;
+
+            #[derive(Debug, PartialEq)]
+            pub struct GraphStats {
+                pub num_nodes: usize,
+                pub num_edges: usize,
+                pub self_loops: usize,
+                // Degree -> number of nodes with that degree
+                pub in_degrees: BTreeMap<usize, usize>,
+                pub out_degrees: BTreeMap<usize, usize>,
+                // Weakly connected components, largest first
+                pub component_sizes: Vec<usize>,
+                // None when the node can't reach every other node
+                pub eccentricities: Vec<Option<usize>>,
+                pub diameter: Option<usize>,
+                pub radius: Option<usize>,
+                pub triangles: usize,
+                pub local_clustering: Vec<f64>,
+                pub global_clustering: f64,
+            }
+
+            impl<T> Graph<T> {
+                pub fn stats(&self) -> GraphStats {
+                    let eccentricities = self.eccentricities();
+                    GraphStats {
+                        num_nodes: self.num_nodes(),
+                        num_edges: self.num_edges(),
+                        self_loops: self.self_loops(),
+                        in_degrees: self.in_degree_histogram(),
+                        out_degrees: self.out_degree_histogram(),
+                        component_sizes: self.component_sizes(),
+                        diameter: diameter(&eccentricities),
+                        radius: radius(&eccentricities),
+                        eccentricities,
+                        triangles: self.triangle_count(),
+                        local_clustering: self.local_clustering(),
+                        global_clustering: self.global_clustering(),
+                    }
+                }
+
+                pub fn self_loops(&self) -> usize {
+                    self.edges
+                        .iter()
+                        .enumerate()
+                        .filter(|(node, edges)| edges.contains_key(node))
+                        .count()
+                }
+
+                pub fn in_degrees(&self) -> Vec<usize> {
+                    let mut degrees = vec![0; self.nodes.len()];
+                    for edges in self.edges.iter() {
+                        for edge_node in edges.keys() {
+                            degrees[*edge_node] += 1;
+                        }
+                    }
+                    degrees
+                }
+
+                pub fn out_degrees(&self) -> Vec<usize> {
+                    self.edges.iter().map(|edges| edges.len()).collect()
+                }
+
+                pub fn in_degree_histogram(&self) -> BTreeMap<usize, usize> {
+                    histogram(self.in_degrees())
+                }
+
+                pub fn out_degree_histogram(&self) -> BTreeMap<usize, usize> {
+                    histogram(self.out_degrees())
+                }
+
+                // Ignores edge direction
+                pub fn components(&self) -> Vec<Vec<NodeLabel>> {
+                    let undirected = self.undirected_edges();
+                    let mut seen = vec![false; self.nodes.len()];
+                    let mut components = Vec::new();
+
+                    for start in 0..self.nodes.len() {
+                        if seen[start] {
+                            continue;
+                        }
+                        seen[start] = true;
+                        let mut component = Vec::new();
+                        let mut queue = VecDeque::new();
+                        queue.push_back(start);
+
+                        while let Some(node) = queue.pop_front() {
+                            component.push(node);
+                            for edge_node in undirected[node].keys() {
+                                if !seen[*edge_node] {
+                                    seen[*edge_node] = true;
+                                    queue.push_back(*edge_node);
+                                }
+                            }
+                        }
+                        components.push(component);
+                    }
+                    components
+                }
+
+                pub fn component_sizes(&self) -> Vec<usize> {
+                    let mut sizes: Vec<usize> = self.components().iter().map(|c| c.len()).collect();
+                    sizes.sort_unstable_by(|a, b| b.cmp(a));
+                    sizes
+                }
+
+                // Longest shortest path (in edges) out of each node
+                pub fn eccentricities(&self) -> Vec<Option<usize>> {
+                    (0..self.nodes.len())
+                        .map(|node| {
+                            self.hop_distances(node).into_iter().try_fold(
+                                0,
+                                |furthest, distance| {
+                                    distance.map(|distance| furthest.max(distance))
+                                },
+                            )
+                        })
+                        .collect()
+                }
+
+                // None if some node can't reach some other node
+                pub fn diameter(&self) -> Option<usize> {
+                    diameter(&self.eccentricities())
+                }
+
+                // Smallest eccentricity among nodes that reach everything
+                pub fn radius(&self) -> Option<usize> {
+                    radius(&self.eccentricities())
+                }
+
+                // Triangles in the undirected view of the graph
+                pub fn triangle_count(&self) -> usize {
+                    let undirected = self.undirected_edges();
+                    let mut triangles = 0;
+                    for (node, edges) in undirected.iter().enumerate() {
+                        for first in edges.range(node + 1..).map(|(n, _)| *n) {
+                            for second in edges.range(first + 1..).map(|(n, _)| *n) {
+                                if undirected[first].contains_key(&second) {
+                                    triangles += 1;
+                                }
+                            }
+                        }
+                    }
+                    triangles
+                }
+
+                // Fraction of each node's neighbour pairs that are themselves neighbours
+                pub fn local_clustering(&self) -> Vec<f64> {
+                    let undirected = self.undirected_edges();
+                    undirected
+                        .iter()
+                        .map(|edges| {
+                            let degree = edges.len();
+                            if degree < 2 {
+                                return 0.0;
+                            }
+                            let mut links = 0;
+                            for first in edges.keys() {
+                                for (second, _) in edges.range(first + 1..) {
+                                    if undirected[*first].contains_key(second) {
+                                        links += 1;
+                                    }
+                                }
+                            }
+                            2.0 * links as f64 / (degree * (degree - 1)) as f64
+                        })
+                        .collect()
+                }
+
+                // Transitivity: 3 * triangles / connected triples
+                pub fn global_clustering(&self) -> f64 {
+                    let triples: usize = self
+                        .undirected_edges()
+                        .iter()
+                        .map(|edges| edges.len() * edges.len().saturating_sub(1) / 2)
+                        .sum();
+                    if triples == 0 {
+                        return 0.0;
+                    }
+                    3.0 * self.triangle_count() as f64 / triples as f64
+                }
+            }
+
+            fn histogram(values: Vec<usize>) -> BTreeMap<usize, usize> {
+                let mut counts = BTreeMap::new();
+                for value in values {
+                    *counts.entry(value).or_insert(0) += 1;
+                }
+                counts
+            }
+
+            fn diameter(eccentricities: &[Option<usize>]) -> Option<usize> {
+                eccentricities
+                    .iter()
+                    .try_fold(None, |furthest: Option<usize>, e| {
+                        e.map(|e| Some(furthest.map_or(e, |f| f.max(e))))
+                    })
+                    .flatten()
+            }
+
+            fn radius(eccentricities: &[Option<usize>]) -> Option<usize> {
+                eccentricities.iter().filter_map(|e| *e).min()
+            }
+
+            fn format_histogram(histogram: &BTreeMap<usize, usize>) -> String {
+                histogram
+                    .iter()
+                    .map(|(degree, count)| format!("{}: {}", degree, count))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+
+            fn format_distance(distance: Option<usize>) -> String {
+                match distance {
+                    Some(distance) => distance.to_string(),
+                    None => "infinite".to_string(),
+                }
+            }
+
+            impl fmt::Display for GraphStats {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    let average_clustering = if self.num_nodes == 0 {
+                        0.0
+                    } else {
+                        self.local_clustering.iter().sum::<f64>() / self.num_nodes as f64
+                    };
+                    // Runs of equal sizes are collapsed, e.g. "5, 1 x 300"
+                    let mut sizes: Vec<(usize, usize)> = Vec::new();
+                    for size in self.component_sizes.iter() {
+                        match sizes.last_mut() {
+                            Some((last, count)) if last == size => *count += 1,
+                            _ => sizes.push((*size, 1)),
+                        }
+                    }
+                    let sizes: Vec<String> = sizes
+                        .iter()
+                        .map(|(size, count)| match count {
+                            1 => size.to_string(),
+                            _ => format!("{} x {}", size, count),
+                        })
+                        .collect();
+
+                    writeln!(f, "nodes: {}", self.num_nodes)?;
+                    writeln!(f, "edges: {}", self.num_edges)?;
+                    writeln!(f, "self loops: {}", self.self_loops)?;
+                    writeln!(f, "in degrees: {}", format_histogram(&self.in_degrees))?;
+                    writeln!(f, "out degrees: {}", format_histogram(&self.out_degrees))?;
+                    writeln!(
+                        f,
+                        "components: {} (sizes {})",
+                        self.component_sizes.len(),
+                        sizes.join(", ")
+                    )?;
+                    writeln!(f, "diameter: {}", format_distance(self.diameter))?;
+                    writeln!(f, "radius: {}", format_distance(self.radius))?;
+                    writeln!(f, "triangles: {}", self.triangles)?;
+                    writeln!(f, "average clustering: {:.4}", average_clustering)?;
+                    write!(f, "global clustering: {:.4}", self.global_clustering)
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn test_counts_and_degrees() {
+                    let graph =
+                        Graph::new_unweighted(vec![0; 4], vec![(0, 1), (0, 2), (1, 2), (3, 3)]);
+                    let stats = graph.stats();
+
+                    assert_eq!(stats.num_nodes, 4);
+                    assert_eq!(stats.num_edges, 4);
+                    assert_eq!(stats.self_loops, 1);
+                    assert_eq!(graph.out_degrees(), vec![2, 1, 0, 1]);
+                    assert_eq!(graph.in_degrees(), vec![0, 1, 2, 1]);
+                    assert_eq!(
+                        stats.out_degrees,
+                        vec![(0, 1), (1, 2), (2, 1)].into_iter().collect()
+                    );
+                    assert_eq!(stats.component_sizes, vec![3, 1]);
+                }
+
+                #[test]
+                fn test_eccentricity() {
+                    let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 2)]);
+                    assert_eq!(graph.eccentricities(), vec![Some(2), None, None]);
+                    assert_eq!(graph.diameter(), None);
+                    assert_eq!(graph.radius(), Some(2));
+
+                    let graph =
+                        Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 0), (1, 2), (2, 1)]);
+                    assert_eq!(graph.eccentricities(), vec![Some(2), Some(1), Some(2)]);
+                    assert_eq!(graph.diameter(), Some(2));
+                    assert_eq!(graph.radius(), Some(1));
+                }
+
+                #[test]
+                fn test_clustering() {
+                    // Triangle 0-1-2 with a tail 2-3
+                    let graph =
+                        Graph::new_unweighted(vec![0; 4], vec![(0, 1), (1, 2), (2, 0), (2, 3)]);
+                    assert_eq!(graph.triangle_count(), 1);
+                    assert_eq!(graph.local_clustering(), vec![1.0, 1.0, 1.0 / 3.0, 0.0]);
+                    assert!((graph.global_clustering() - 0.6).abs() < 1e-9);
+                }
+            }
+        }
+
+        // Cliques in the undirected view of the graph (direction and self loops are ignored)
+        pub mod cliques {
+            use super::{EdgeWeight, Graph, NodeLabel};
+            use std::collections::{BTreeMap, BTreeSet};
+
+            type Adjacency = Vec<BTreeMap<NodeLabel, EdgeWeight>>;
+
+            impl<T> Graph<T> {
+                // Repeatedly remove a node of smallest remaining degree.
+                // Each node has at most `degeneracy` neighbours later in the ordering.
+                pub fn degeneracy_ordering(&self) -> Vec<NodeLabel> {
+                    let undirected = self.undirected_edges();
+                    let mut degrees: Vec<usize> = undirected.iter().map(|e| e.len()).collect();
+                    let mut remaining: BTreeSet<(usize, NodeLabel)> =
+                        degrees.iter().cloned().zip(0..).collect();
+                    let mut removed = vec![false; degrees.len()];
+                    let mut ordering = Vec::with_capacity(degrees.len());
+
+                    while let Some((_degree, node)) = remaining.iter().next().cloned() {
+                        remaining.remove(&(degrees[node], node));
+                        removed[node] = true;
+                        ordering.push(node);
+
+                        for edge_node in undirected[node].keys() {
+                            if !removed[*edge_node] {
+                                remaining.remove(&(degrees[*edge_node], *edge_node));
+                                degrees[*edge_node] -= 1;
+                                remaining.insert((degrees[*edge_node], *edge_node));
+                            }
+                        }
+                    }
+                    ordering
+                }
+
+                // Bron-Kerbosch with pivoting, started from each node in degeneracy order.
+                // Every clique is sorted, and so is the list of cliques.
+                pub fn maximal_cliques(&self) -> Vec<Vec<NodeLabel>> {
+                    let undirected = self.undirected_edges();
+                    let ordering = self.degeneracy_ordering();
+                    let mut position = vec![0; ordering.len()];
+                    for (index, node) in ordering.iter().enumerate() {
+                        position[*node] = index;
+                    }
+
+                    let mut cliques = Vec::new();
+                    for node in ordering.iter() {
+                        let (later, earlier): (BTreeSet<NodeLabel>, BTreeSet<NodeLabel>) =
+                            undirected[*node]
+                                .keys()
+                                .partition(|edge_node| position[**edge_node] > position[*node]);
+                        let mut clique = vec![*node];
+                        bron_kerbosch(&undirected, &mut clique, later, earlier, &mut cliques);
+                    }
+
+                    for clique in cliques.iter_mut() {
+                        clique.sort_unstable();
+                    }
+                    cliques.sort();
+                    cliques
+                }
+
+                // Branch and bound, pruning with a greedy colouring of the candidates.
+                // Exponential in the worst case, so meant for small graphs.
+                pub fn maximum_clique(&self) -> Vec<NodeLabel> {
+                    let undirected = self.undirected_edges();
+                    let mut best = Vec::new();
+                    let mut current = Vec::new();
+                    let candidates: Vec<NodeLabel> = (0..self.nodes.len()).collect();
+
+                    expand(&undirected, &mut current, candidates, &mut best);
+                    best.sort_unstable();
+                    best
+                }
+            }
+
+            fn bron_kerbosch(
+                undirected: &Adjacency,
+                clique: &mut Vec<NodeLabel>,
+                mut candidates: BTreeSet<NodeLabel>,
+                mut excluded: BTreeSet<NodeLabel>,
+                cliques: &mut Vec<Vec<NodeLabel>>,
+            ) {
+                // Pick the pivot that covers the most candidates, then only branch on
+                // candidates outside its neighbourhood
+                let pivot = match candidates.union(&excluded).max_by_key(|node| {
+                    candidates
+                        .iter()
+                        .filter(|candidate| undirected[**node].contains_key(candidate))
+                        .count()
+                }) {
+                    Some(pivot) => *pivot,
+                    None => {
+                        cliques.push(clique.clone());
+                        return;
+                    }
+                };
+
+                let branches: Vec<NodeLabel> = candidates
+                    .iter()
+                    .filter(|candidate| !undirected[pivot].contains_key(candidate))
+                    .cloned()
+                    .collect();
+
+                for node in branches {
+                    let neighbours = &undirected[node];
+                    let next_candidates = candidates
+                        .iter()
+                        .filter(|n| neighbours.contains_key(n))
+                        .cloned()
+                        .collect();
+                    let next_excluded = excluded
+                        .iter()
+                        .filter(|n| neighbours.contains_key(n))
+                        .cloned()
+                        .collect();
+
+                    clique.push(node);
+                    bron_kerbosch(undirected, clique, next_candidates, next_excluded, cliques);
+                    clique.pop();
+
+                    candidates.remove(&node);
+                    excluded.insert(node);
+                }
+            }
+
+            fn expand(
+                undirected: &Adjacency,
+                current: &mut Vec<NodeLabel>,
+                candidates: Vec<NodeLabel>,
+                best: &mut Vec<NodeLabel>,
+            ) {
+                let (ordered, colours) = colour_sort(undirected, &candidates);
+
+                // Highest colours first; a candidate coloured k can grow the clique by at most k
+                for index in (0..ordered.len()).rev() {
+                    if current.len() + colours[index] <= best.len() {
+                        return;
+                    }
+                    let node = ordered[index];
+                    let next_candidates: Vec<NodeLabel> = ordered[..index]
+                        .iter()
+                        .filter(|candidate| undirected[node].contains_key(candidate))
+                        .cloned()
+                        .collect();
+
+                    current.push(node);
+                    if next_candidates.is_empty() {
+                        if current.len() > best.len() {
+                            *best = current.clone();
+                        }
+                    } else {
+                        expand(undirected, current, next_candidates, best);
+                    }
+                    current.pop();
+                }
+            }
+
+            // Greedily colour the candidates and return them ordered by colour (1-based)
+            fn colour_sort(
+                undirected: &Adjacency,
+                candidates: &[NodeLabel],
+            ) -> (Vec<NodeLabel>, Vec<usize>) {
+                let mut classes: Vec<Vec<NodeLabel>> = Vec::new();
+                for candidate in candidates.iter() {
+                    let class = classes.iter().position(|class| {
+                        class
+                            .iter()
+                            .all(|node| !undirected[*candidate].contains_key(node))
+                    });
+                    match class {
+                        Some(index) => classes[index].push(*candidate),
+                        None => classes.push(vec![*candidate]),
+                    }
+                }
+
+                let mut ordered = Vec::with_capacity(candidates.len());
+                let mut colours = Vec::with_capacity(candidates.len());
+                for (index, class) in classes.into_iter().enumerate() {
+                    for node in class {
+                        ordered.push(node);
+                        colours.push(index + 1);
+                    }
+                }
+                (ordered, colours)
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                fn two_triangles_and_a_square() -> Graph<i32> {
+                    // Triangles 0-1-2 and 1-2-3 share an edge, 4-5-6-7 is a square, 8 is alone
+                    Graph::new_unweighted(
+                        vec![0; 9],
+                        vec![
+                            (0, 1),
+                            (0, 2),
+                            (1, 2),
+                            (3, 1),
+                            (2, 3),
+                            (4, 5),
+                            (5, 6),
+                            (6, 7),
+                            (7, 4),
+                        ],
+                    )
+                }
+
+                #[test]
+                fn test_maximal_cliques() {
+                    let graph = two_triangles_and_a_square();
+                    assert_eq!(
+                        graph.maximal_cliques(),
+                        vec![
+                            vec![0, 1, 2],
+                            vec![1, 2, 3],
+                            vec![4, 5],
+                            vec![4, 7],
+                            vec![5, 6],
+                            vec![6, 7],
+                            vec![8],
+                        ]
+                    );
+                }
+
+                #[test]
+                fn test_degeneracy_ordering() {
+                    let graph = two_triangles_and_a_square();
+                    let ordering = graph.degeneracy_ordering();
+                    assert_eq!(ordering.len(), 9);
+                    assert_eq!(ordering[0], 8);
+                }
+
+                #[test]
+                fn test_maximum_clique() {
+                    let graph = two_triangles_and_a_square();
+                    assert_eq!(graph.maximum_clique().len(), 3);
+
+                    // K4 on 2..6 hidden among a path
+                    let mut edges = vec![(0, 1), (1, 2), (5, 6), (6, 7)];
+                    for a in 2..6 {
+                        for b in a + 1..6 {
+                            edges.push((a, b));
+                        }
+                    }
+                    let graph = Graph::new_unweighted(vec![0; 8], edges);
+                    assert_eq!(graph.maximum_clique(), vec![2, 3, 4, 5]);
+
+                    let graph: Graph<i32> = Graph::new(vec![], vec![]);
+                    assert!(graph.maximum_clique().is_empty());
+                }
+            }
+        }
+
+        // Vertex colourings of the undirected view: neighbours never share a colour.
+        // Colours are numbered from 0. Self loops are ignored.
+        pub mod colouring {
+            use super::{EdgeWeight, Graph, NodeLabel};
+            use std::collections::{BTreeMap, BTreeSet};
+
+            type Adjacency = Vec<BTreeMap<NodeLabel, EdgeWeight>>;
+
+            #[derive(Debug, Clone, Copy, PartialEq)]
+            pub enum ColouringOrder {
+                Natural,
+                LargestFirst,
+                SmallestLast,
+            }
+
+            impl<T> Graph<T> {
+                // Give each node, in the chosen order, the smallest colour its neighbours don't use
+                pub fn greedy_colouring(&self, order: ColouringOrder) -> Vec<usize> {
+                    let undirected = self.undirected_edges();
+                    let ordering = match order {
+                        ColouringOrder::Natural => (0..self.nodes.len()).collect(),
+                        ColouringOrder::LargestFirst => {
+                            let mut ordering: Vec<NodeLabel> = (0..self.nodes.len()).collect();
+                            ordering.sort_by_key(|node| std::cmp::Reverse(undirected[*node].len()));
+                            ordering
+                        }
+                        ColouringOrder::SmallestLast => {
+                            let mut ordering = self.degeneracy_ordering();
+                            ordering.reverse();
+                            ordering
+                        }
+                    };
+
+                    let mut colours = vec![None; self.nodes.len()];
+                    for node in ordering {
+                        colours[node] = Some(smallest_free_colour(&undirected, &colours, node));
+                    }
+                    colours.into_iter().map(|c| c.unwrap_or(0)).collect()
+                }
+
+                // Brelaz's DSatur: always colour the node whose neighbours already use the
+                // most distinct colours, breaking ties by degree
+                pub fn dsatur_colouring(&self) -> Vec<usize> {
+                    let undirected = self.undirected_edges();
+                    dsatur(&undirected).0
+                }
+
+                // Backtracking search for a colouring with as few colours as possible.
+                // Exponential in the worst case, so meant for small graphs.
+                pub fn exact_colouring(&self) -> Vec<usize> {
+                    let undirected = self.undirected_edges();
+                    let (mut best, ordering) = dsatur(&undirected);
+                    let mut num_colours = count_colours(&best);
+                    let lower_bound = self.maximum_clique().len();
+
+                    while num_colours > lower_bound {
+                        let mut colours = vec![None; self.nodes.len()];
+                        if !backtrack(&undirected, &ordering, 0, num_colours - 1, &mut colours) {
+                            break;
+                        }
+                        best = colours.into_iter().map(|c| c.unwrap_or(0)).collect();
+                        num_colours = count_colours(&best);
+                    }
+                    best
+                }
+
+                pub fn chromatic_number(&self) -> usize {
+                    count_colours(&self.exact_colouring())
+                }
+
+                pub fn is_valid_colouring(&self, colours: &[usize]) -> bool {
+                    colours.len() == self.nodes.len()
+                        && self
+                            .undirected_edges()
+                            .iter()
+                            .enumerate()
+                            .all(|(node, edges)| {
+                                edges
+                                    .keys()
+                                    .all(|edge_node| colours[node] != colours[*edge_node])
+                            })
+                }
+            }
+
+            pub fn count_colours(colours: &[usize]) -> usize {
+                colours.iter().max().map_or(0, |c| c + 1)
+            }
+
+            fn smallest_free_colour(
+                undirected: &Adjacency,
+                colours: &[Option<usize>],
+                node: NodeLabel,
+            ) -> usize {
+                let used: BTreeSet<usize> = undirected[node]
+                    .keys()
+                    .filter_map(|edge_node| colours[*edge_node])
+                    .collect();
+                (0..).find(|colour| !used.contains(colour)).unwrap_or(0)
+            }
+
+            // Returns the colouring and the order nodes were coloured in
+            fn dsatur(undirected: &Adjacency) -> (Vec<usize>, Vec<NodeLabel>) {
+                let num_nodes = undirected.len();
+                let mut colours = vec![None; num_nodes];
+                let mut neighbour_colours = vec![BTreeSet::new(); num_nodes];
+                let mut ordering = Vec::with_capacity(num_nodes);
+
+                for _ in 0..num_nodes {
+                    let node = (0..num_nodes)
+                        .filter(|node| colours[*node].is_none())
+                        .max_by_key(|node| {
+                            (
+                                neighbour_colours[*node].len(),
+                                undirected[*node].len(),
+                                std::cmp::Reverse(*node),
+                            )
+                        })
+                        .unwrap_or(0);
+
+                    let colour = smallest_free_colour(undirected, &colours, node);
+                    colours[node] = Some(colour);
+                    ordering.push(node);
+                    for edge_node in undirected[node].keys() {
+                        neighbour_colours[*edge_node].insert(colour);
+                    }
+                }
+                (
+                    colours.into_iter().map(|c| c.unwrap_or(0)).collect(),
+                    ordering,
+                )
+            }
+
+            // Try to colour ordering[index..] with colours below max_colours
+            fn backtrack(
+                undirected: &Adjacency,
+                ordering: &[NodeLabel],
+                index: usize,
+                max_colours: usize,
+                colours: &mut Vec<Option<usize>>,
+            ) -> bool {
+                let node = match ordering.get(index) {
+                    Some(node) => *node,
+                    None => return true,
+                };
+
+                // Colours are interchangeable, so never open more than one new colour at a time
+                let used = colours.iter().filter_map(|c| *c).max().map_or(0, |c| c + 1);
+                for colour in 0..max_colours.min(used + 1) {
+                    let conflict = undirected[node]
+                        .keys()
+                        .any(|edge_node| colours[*edge_node] == Some(colour));
+                    if conflict {
+                        continue;
+                    }
+                    colours[node] = Some(colour);
+                    if backtrack(undirected, ordering, index + 1, max_colours, colours) {
+                        return true;
+                    }
+                    colours[node] = None;
+                }
+                false
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                fn cycle(length: usize) -> Graph<i32> {
+                    let edges = (0..length).map(|i| (i, (i + 1) % length)).collect();
+                    Graph::new_unweighted(vec![0; length], edges)
+                }
+
+                #[test]
+                fn test_greedy_colouring() {
+                    let graph = cycle(6);
+                    for order in [
+                        ColouringOrder::Natural,
+                        ColouringOrder::LargestFirst,
+                        ColouringOrder::SmallestLast,
+                    ]
+                    .iter()
+                    {
+                        let colours = graph.greedy_colouring(*order);
+                        assert!(graph.is_valid_colouring(&colours));
+                        assert_eq!(count_colours(&colours), 2);
+                    }
+                }
+
+                #[test]
+                fn test_dsatur_colouring() {
+                    let graph = cycle(5);
+                    let colours = graph.dsatur_colouring();
+                    assert!(graph.is_valid_colouring(&colours));
+                    assert_eq!(count_colours(&colours), 3);
+                }
+
+                #[test]
+                fn test_exact_colouring() {
+                    assert_eq!(cycle(4).chromatic_number(), 2);
+                    assert_eq!(cycle(7).chromatic_number(), 3);
+
+                    // Wheel with an odd rim needs four colours, but has no K4
+                    let mut edges: Vec<(usize, usize)> = (0..5).map(|i| (i, (i + 1) % 5)).collect();
+                    edges.extend((0..5).map(|i| (5, i)));
+                    let graph = Graph::new_unweighted(vec![0; 6], edges);
+                    let colours = graph.exact_colouring();
+                    assert!(graph.is_valid_colouring(&colours));
+                    assert_eq!(count_colours(&colours), 4);
+                }
+
+                #[test]
+                fn test_is_valid_colouring() {
+                    let graph = cycle(3);
+                    assert!(graph.is_valid_colouring(&[0, 1, 2]));
+                    assert!(!graph.is_valid_colouring(&[0, 1, 1]));
+                    assert!(!graph.is_valid_colouring(&[0, 1]));
+                }
+            }
+        }
+
+        // Community detection on the undirected view. Edge weights should be positive.
+        // Community ids run from 0 in order of each community's lowest node.
+        pub mod communities {
+            use super::{Graph, NodeLabel};
+            use crate::random::Rng;
+            use std::collections::{BTreeMap, HashMap};
+
+            type WeightedAdjacency = Vec<BTreeMap<NodeLabel, f64>>;
+
+            impl<T> Graph<T> {
+                // Newman-Girvan modularity of a partition
+                pub fn modularity(&self, communities: &[usize]) -> f64 {
+                    modularity(&self.weighted_undirected(), communities)
+                }
+
+                // Every node repeatedly adopts the label with the most edge weight among its
+                // neighbours, visiting nodes in a shuffled order. Ties are broken at random.
+                pub fn label_propagation(
+                    &self,
+                    seed: u64,
+                    max_iterations: usize,
+                ) -> (Vec<usize>, f64) {
+                    let undirected = self.weighted_undirected();
+                    let mut rng = Rng::new(seed);
+                    let mut labels: Vec<usize> = (0..self.nodes.len()).collect();
+                    let mut order: Vec<NodeLabel> = (0..self.nodes.len()).collect();
+
+                    for _ in 0..max_iterations {
+                        rng.shuffle(&mut order);
+                        let mut changed = false;
+
+                        for node in order.iter() {
+                            let mut weights: BTreeMap<usize, f64> = BTreeMap::new();
+                            for (edge_node, weight) in undirected[*node].iter() {
+                                *weights.entry(labels[*edge_node]).or_insert(0.0) += weight;
+                            }
+                            let best = weights.values().cloned().fold(f64::MIN, f64::max);
+                            let tied: Vec<usize> = weights
+                                .iter()
+                                .filter(|(_label, weight)| **weight == best)
+                                .map(|(label, _weight)| *label)
+                                .collect();
+
+                            // Keeping the current label when it's among the best lets us converge
+                            if tied.is_empty() || tied.contains(&labels[*node]) {
+                                continue;
+                            }
+                            labels[*node] = tied[rng.below(tied.len())];
+                            changed = true;
+                        }
+
+                        if !changed {
+                            break;
+                        }
+                    }
+
+                    let labels = renumber(&labels);
+                    let modularity = modularity(&undirected, &labels);
+                    (labels, modularity)
+                }
+
+                // Blondel et al.: move single nodes to whichever neighbouring community gains
+                // the most modularity, then collapse communities into nodes and repeat
+                pub fn louvain(&self, seed: u64) -> (Vec<usize>, f64) {
+                    let original = self.weighted_undirected();
+                    let mut rng = Rng::new(seed);
+
+                    // Self loops on the collapsed graph hold twice the weight inside a community,
+                    // so every node's degree is still the sum of its row
+                    let mut adjacency = original.clone();
+                    let mut membership: Vec<usize> = (0..self.nodes.len()).collect();
+
+                    loop {
+                        let (communities, moved) = local_moving(&adjacency, &mut rng);
+                        if !moved {
+                            break;
+                        }
+                        let communities = renumber(&communities);
+                        for community in membership.iter_mut() {
+                            *community = communities[*community];
+                        }
+                        adjacency = collapse(&adjacency, &communities);
+                    }
+
+                    let labels = renumber(&membership);
+                    let modularity = modularity(&original, &labels);
+                    (labels, modularity)
+                }
+
+                fn weighted_undirected(&self) -> WeightedAdjacency {
+                    self.undirected_edges()
+                        .into_iter()
+                        .map(|edges| {
+                            edges
+                                .into_iter()
+                                .map(|(node, weight)| (node, f64::from(weight)))
+                                .collect()
+                        })
+                        .collect()
+                }
+            }
+
+            fn modularity(adjacency: &WeightedAdjacency, communities: &[usize]) -> f64 {
+                let degrees: Vec<f64> =
+                    adjacency.iter().map(|edges| edges.values().sum()).collect();
+                let total: f64 = degrees.iter().sum();
+                if total == 0.0 {
+                    return 0.0;
+                }
+
+                let mut inside: HashMap<usize, f64> = HashMap::new();
+                let mut totals: HashMap<usize, f64> = HashMap::new();
+                for (node, edges) in adjacency.iter().enumerate() {
+                    *totals.entry(communities[node]).or_insert(0.0) += degrees[node];
+                    for (edge_node, weight) in edges.iter() {
+                        if communities[node] == communities[*edge_node] {
+                            *inside.entry(communities[node]).or_insert(0.0) += weight;
+                        }
+                    }
+                }
+
+                totals
+                    .iter()
+                    .map(|(community, degree)| {
+                        inside.get(community).unwrap_or(&0.0) / total - (degree / total).powi(2)
+                    })
+                    .sum()
+            }
+
+            // One Louvain pass. Returns the community of each node and whether anything moved.
+            fn local_moving(adjacency: &WeightedAdjacency, rng: &mut Rng) -> (Vec<usize>, bool) {
+                let num_nodes = adjacency.len();
+                let degrees: Vec<f64> =
+                    adjacency.iter().map(|edges| edges.values().sum()).collect();
+                let total: f64 = degrees.iter().sum();
+                let mut communities: Vec<usize> = (0..num_nodes).collect();
+                let mut community_degrees = degrees.clone();
+                let mut order: Vec<NodeLabel> = (0..num_nodes).collect();
+                let mut moved = false;
+
+                if total == 0.0 {
+                    return (communities, false);
+                }
+
+                loop {
+                    rng.shuffle(&mut order);
+                    let mut improved = false;
+
+                    for node in order.iter() {
+                        let current = communities[*node];
+                        community_degrees[current] -= degrees[*node];
+
+                        let mut links: BTreeMap<usize, f64> = BTreeMap::new();
+                        links.insert(current, 0.0);
+                        for (edge_node, weight) in adjacency[*node].iter() {
+                            if edge_node != node {
+                                *links.entry(communities[*edge_node]).or_insert(0.0) += weight;
+                            }
+                        }
+
+                        let gain = |community: usize, link: f64| {
+                            link - community_degrees[community] * degrees[*node] / total
+                        };
+                        let mut best = current;
+                        let mut best_gain = gain(current, links[&current]);
+                        for (community, link) in links.iter() {
+                            let candidate = gain(*community, *link);
+                            if candidate > best_gain + 1e-12 {
+                                best = *community;
+                                best_gain = candidate;
+                            }
+                        }
+
+                        community_degrees[best] += degrees[*node];
+                        if best != current {
+                            communities[*node] = best;
+                            improved = true;
+                            moved = true;
+                        }
+                    }
+
+                    if !improved {
+                        break;
+                    }
+                }
+                (communities, moved)
+            }
+
+            fn collapse(adjacency: &WeightedAdjacency, communities: &[usize]) -> WeightedAdjacency {
+                let num_communities = communities.iter().max().map_or(0, |c| c + 1);
+                let mut collapsed = vec![BTreeMap::new(); num_communities];
+                for (node, edges) in adjacency.iter().enumerate() {
+                    for (edge_node, weight) in edges.iter() {
+                        *collapsed[communities[node]]
+                            .entry(communities[*edge_node])
+                            .or_insert(0.0) += weight;
+                    }
+                }
+                collapsed
+            }
+
+            fn renumber(labels: &[usize]) -> Vec<usize> {
+                let mut ids = HashMap::new();
+                labels
+                    .iter()
+                    .map(|label| {
+                        let next = ids.len();
+                        *ids.entry(*label).or_insert(next)
+                    })
+                    .collect()
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                fn two_triangles(bridge: bool) -> Graph<i32> {
+                    let mut edges = vec![(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)];
+                    if bridge {
+                        edges.push((2, 3));
+                    }
+                    Graph::new_unweighted(vec![0; 6], edges)
+                }
+
+                #[test]
+                fn test_modularity() {
+                    let graph = two_triangles(true);
+                    let modularity = graph.modularity(&[0, 0, 0, 1, 1, 1]);
+                    assert!((modularity - (6.0 / 7.0 - 0.5)).abs() < 1e-9);
+                    assert!(graph.modularity(&[0; 6]).abs() < 1e-9);
+                }
+
+                #[test]
+                fn test_label_propagation() {
+                    let graph = two_triangles(false);
+                    let (labels, modularity) = graph.label_propagation(7, 100);
+                    assert_eq!(labels, vec![0, 0, 0, 1, 1, 1]);
+                    assert!((modularity - 0.5).abs() < 1e-9);
+
+                    // Same seed, same answer
+                    let graph = two_triangles(true);
+                    assert_eq!(
+                        graph.label_propagation(3, 100),
+                        graph.label_propagation(3, 100)
+                    );
+                }
+
+                #[test]
+                fn test_louvain() {
+                    let graph = two_triangles(true);
+                    for seed in 0..5 {
+                        let (labels, modularity) = graph.louvain(seed);
+                        assert_eq!(labels, vec![0, 0, 0, 1, 1, 1]);
+                        assert!((modularity - (6.0 / 7.0 - 0.5)).abs() < 1e-9);
+                    }
+
+                    // Heavy edges pull 2 and 3 together
+                    let graph = Graph::new(
+                        vec![0; 4],
+                        vec![(0, 1, 10), (1, 2, 1), (2, 3, 10), (3, 0, 1)],
+                    );
+                    let (labels, _modularity) = graph.louvain(1);
+                    assert_eq!(labels, vec![0, 0, 1, 1]);
+                }
+            }
+        }
+
+        // Minimum cuts of the undirected view. Edge weights should be non-negative.
+        pub mod cuts {
+            use super::{Graph, NodeLabel};
+            use std::collections::{BTreeMap, VecDeque};
+
+            type Capacities = Vec<BTreeMap<NodeLabel, i64>>;
+
+            #[derive(Debug, PartialEq)]
+            pub struct Cut {
+                pub weight: i64,
+                pub side: Vec<NodeLabel>,
+                pub other_side: Vec<NodeLabel>,
+            }
+
+            // Every pair's minimum cut is the lightest edge on their path through the tree
+            #[derive(Debug)]
+            pub struct GomoryHuTree {
+                parent: Vec<NodeLabel>,
+                weight: Vec<i64>,
+                depth: Vec<usize>,
+            }
+
+            impl GomoryHuTree {
+                // (node, parent, weight) for every node but the root, node 0
+                pub fn edges(&self) -> Vec<(NodeLabel, NodeLabel, i64)> {
+                    (1..self.parent.len())
+                        .map(|node| (node, self.parent[node], self.weight[node]))
+                        .collect()
+                }
+
+                // Weight of the minimum cut separating u and v, or None when u == v
+                pub fn min_cut(&self, u: NodeLabel, v: NodeLabel) -> Option<i64> {
+                    let (mut u, mut v) = (u, v);
+                    let mut lightest = None;
+                    while u != v {
+                        if self.depth[u] < self.depth[v] {
+                            std::mem::swap(&mut u, &mut v);
+                        }
+                        let weight = self.weight[u];
+                        lightest = Some(lightest.map_or(weight, |w: i64| w.min(weight)));
+                        u = self.parent[u];
+                    }
+                    lightest
+                }
+            }
+
+            impl<T> Graph<T> {
+                // Stoer-Wagner: repeated maximum adjacency searches, merging the last two
+                // nodes of each phase. None if there are fewer than two nodes.
+                pub fn stoer_wagner(&self) -> Option<Cut> {
+                    let num_nodes = self.nodes.len();
+                    if num_nodes < 2 {
+                        return None;
+                    }
+
+                    let mut weights = vec![vec![0i64; num_nodes]; num_nodes];
+                    for (node, edges) in self.undirected_edges().iter().enumerate() {
+                        for (edge_node, weight) in edges.iter() {
+                            weights[node][*edge_node] = i64::from(*weight);
+                        }
+                    }
+
+                    let mut groups: Vec<Vec<NodeLabel>> = (0..num_nodes).map(|n| vec![n]).collect();
+                    let mut active = vec![true; num_nodes];
+                    let mut best: Option<(i64, Vec<NodeLabel>)> = None;
+
+                    for phase in (1..num_nodes).rev() {
+                        let mut connection = vec![0i64; num_nodes];
+                        let mut added = vec![false; num_nodes];
+                        let mut previous = 0;
+
+                        for step in 0..=phase {
+                            let next = (0..num_nodes)
+                                .filter(|n| active[*n] && !added[*n])
+                                .max_by_key(|n| (connection[*n], std::cmp::Reverse(*n)))
+                                .unwrap_or(0);
+
+                            if step < phase {
+                                added[next] = true;
+                                for (total, weight) in
+                                    connection.iter_mut().zip(weights[next].iter())
+                                {
+                                    *total += weight;
+                                }
+                                previous = next;
+                                continue;
+                            }
+
+                            // The cut of the phase separates the last node from everything else
+                            let lighter = match best.as_ref() {
+                                Some((weight, _)) => connection[next] < *weight,
+                                None => true,
+                            };
+                            if lighter {
+                                best = Some((connection[next], groups[next].clone()));
+                            }
+
+                            let merged = std::mem::take(&mut groups[next]);
+                            groups[previous].extend(merged);
+                            let merged_weights = weights[next].clone();
+                            for (other, weight) in merged_weights.iter().enumerate() {
+                                weights[previous][other] += weight;
+                                weights[other][previous] = weights[previous][other];
+                            }
+                            active[next] = false;
+                        }
+                    }
+
+                    best.map(|(weight, mut side)| {
+                        side.sort_unstable();
+                        let other_side = (0..num_nodes).filter(|n| !side.contains(n)).collect();
+                        Cut {
+                            weight,
+                            side,
+                            other_side,
+                        }
+                    })
+                }
+
+                // Gusfield's algorithm: n - 1 maximum flow computations
+                pub fn gomory_hu_tree(&self) -> GomoryHuTree {
+                    let num_nodes = self.nodes.len();
+                    let capacities = self.capacities();
+                    let mut parent = vec![0; num_nodes];
+                    let mut weight = vec![0i64; num_nodes];
+
+                    for source in 1..num_nodes {
+                        let sink = parent[source];
+                        let (flow, source_side) = max_flow(&capacities, source, sink);
+                        weight[source] = flow;
+
+                        for node in 0..num_nodes {
+                            if node != source && source_side[node] && parent[node] == sink {
+                                parent[node] = source;
+                            }
+                        }
+                        if source_side[parent[sink]] {
+                            parent[source] = parent[sink];
+                            parent[sink] = source;
+                            weight[source] = weight[sink];
+                            weight[sink] = flow;
+                        }
+                    }
+
+                    // Parents can point forwards after the swaps above, so find depths by walking up
+                    let mut depth = vec![None; num_nodes];
+                    if num_nodes > 0 {
+                        depth[0] = Some(0);
+                    }
+                    for node in 0..num_nodes {
+                        let mut path = Vec::new();
+                        let mut current = node;
+                        while depth[current].is_none() {
+                            path.push(current);
+                            current = parent[current];
+                        }
+                        let mut known = depth[current].unwrap_or(0);
+                        for node in path.into_iter().rev() {
+                            known += 1;
+                            depth[node] = Some(known);
+                        }
+                    }
+
+                    GomoryHuTree {
+                        parent,
+                        weight,
+                        depth: depth.into_iter().map(|d| d.unwrap_or(0)).collect(),
+                    }
+                }
+
+                fn capacities(&self) -> Capacities {
+                    self.undirected_edges()
+                        .into_iter()
+                        .map(|edges| edges.into_iter().map(|(n, w)| (n, i64::from(w))).collect())
+                        .collect()
+                }
+            }
+
+            // Edmonds-Karp. Returns the flow value and which nodes stay reachable from the
+            // source in the residual graph, i.e. the source side of a minimum cut.
+            fn max_flow(
+                capacities: &Capacities,
+                source: NodeLabel,
+                sink: NodeLabel,
+            ) -> (i64, Vec<bool>) {
+                let num_nodes = capacities.len();
+                let mut residual = capacities.clone();
+                let mut flow = 0;
+
+                loop {
+                    let mut parents = vec![None; num_nodes];
+                    let mut seen = vec![false; num_nodes];
+                    let mut queue = VecDeque::new();
+                    seen[source] = true;
+                    queue.push_back(source);
+
+                    while let Some(node) = queue.pop_front() {
+                        for (edge_node, capacity) in residual[node].iter() {
+                            if *capacity > 0 && !seen[*edge_node] {
+                                seen[*edge_node] = true;
+                                parents[*edge_node] = Some(node);
+                                queue.push_back(*edge_node);
+                            }
+                        }
+                    }
+
+                    if !seen[sink] {
+                        return (flow, seen);
+                    }
+
+                    let mut bottleneck = i64::MAX;
+                    let mut node = sink;
+                    while let Some(parent) = parents[node] {
+                        bottleneck = bottleneck.min(residual[parent][&node]);
+                        node = parent;
+                    }
+
+                    let mut node = sink;
+                    while let Some(parent) = parents[node] {
+                        *residual[parent].entry(node).or_insert(0) -= bottleneck;
+                        *residual[node].entry(parent).or_insert(0) += bottleneck;
+                        node = parent;
+                    }
+                    flow += bottleneck;
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                // The example from Stoer and Wagner's paper, relabelled from 0
+                fn paper_graph() -> Graph<i32> {
+                    Graph::new(
+                        vec![0; 8],
+                        vec![
+                            (0, 1, 2),
+                            (0, 4, 3),
+                            (1, 2, 3),
+                            (1, 4, 2),
+                            (1, 5, 2),
+                            (2, 3, 4),
+                            (2, 6, 2),
+                            (3, 6, 2),
+                            (3, 7, 2),
+                            (4, 5, 3),
+                            (5, 6, 1),
+                            (6, 7, 3),
+                        ],
+                    )
+                }
+
+                #[test]
+                fn test_stoer_wagner() {
+                    let cut = paper_graph().stoer_wagner().unwrap();
+                    assert_eq!(cut.weight, 4);
+                    let mut sides = vec![cut.side, cut.other_side];
+                    sides.sort();
+                    assert_eq!(sides, vec![vec![0, 1, 4, 5], vec![2, 3, 6, 7]]);
+
+                    // Disconnected graphs have a free cut
+                    let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 1), (2, 3)]);
+                    assert_eq!(graph.stoer_wagner().unwrap().weight, 0);
+
+                    let graph = Graph::new_unweighted(vec![0], vec![]);
+                    assert_eq!(graph.stoer_wagner(), None);
+                }
+
+                #[test]
+                fn test_gomory_hu_tree() {
+                    let graph = paper_graph();
+                    let tree = graph.gomory_hu_tree();
+                    let capacities = graph.capacities();
+
+                    assert_eq!(tree.edges().len(), 7);
+                    for u in 0..8 {
+                        assert_eq!(tree.min_cut(u, u), None);
+                        for v in 0..8 {
+                            if u != v {
+                                assert_eq!(tree.min_cut(u, v), Some(max_flow(&capacities, u, v).0));
+                            }
+                        }
+                    }
+                    assert_eq!(tree.edges().iter().map(|e| e.2).min(), Some(4));
+                }
+            }
+        }
+
+        // Minimum spanning arborescence of a directed graph (Chu-Liu/Edmonds)
+        pub mod arborescence {
+            use super::{Graph, NodeLabel};
+            use std::error::Error;
+            use std::fmt;
+
+            type WeightedEdge = (NodeLabel, NodeLabel, i64);
+
+            #[derive(Debug, PartialEq)]
+            pub struct Arborescence {
+                // None for the root
+                pub parents: Vec<Option<NodeLabel>>,
+                pub weight: i64,
+            }
+
+            #[derive(Debug, PartialEq)]
+            pub enum ArborescenceError {
+                RootOutOfRange(NodeLabel),
+                Unreachable(Vec<NodeLabel>),
+            }
+
+            impl fmt::Display for ArborescenceError {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        ArborescenceError::RootOutOfRange(root) => {
+                            write!(f, "root {} is not a node in the graph", root)
+                        }
+                        ArborescenceError::Unreachable(nodes) => {
+                            write!(f, "nodes {:?} are unreachable from the root", nodes)
+                        }
+                    }
+                }
+            }
+
+            impl Error for ArborescenceError {}
+
+            impl<T> Graph<T> {
+                // Cheapest set of edges that reaches every node from root
+                pub fn min_arborescence(
+                    &self,
+                    root: NodeLabel,
+                ) -> Result<Arborescence, ArborescenceError> {
+                    let num_nodes = self.nodes.len();
+                    if root >= num_nodes {
+                        return Err(ArborescenceError::RootOutOfRange(root));
+                    }
+
+                    let unreachable: Vec<NodeLabel> = self
+                        .hop_distances(root)
+                        .iter()
+                        .enumerate()
+                        .filter(|(_node, distance)| distance.is_none())
+                        .map(|(node, _distance)| node)
+                        .collect();
+                    if !unreachable.is_empty() {
+                        return Err(ArborescenceError::Unreachable(unreachable));
+                    }
+
+                    let edges: Vec<WeightedEdge> = self
+                        .edge_list()
+                        .into_iter()
+                        .map(|(source, target, weight)| (source, target, i64::from(weight)))
+                        .collect();
+
+                    let mut parents = vec![None; num_nodes];
+                    let mut weight = 0;
+                    for index in chu_liu_edmonds(num_nodes, &edges, root) {
+                        let (source, target, edge_weight) = edges[index];
+                        parents[target] = Some(source);
+                        weight += edge_weight;
+                    }
+                    Ok(Arborescence { parents, weight })
+                }
+            }
+
+            // Returns indices into edges. Every node must be reachable from root.
+            fn chu_liu_edmonds(
+                num_nodes: usize,
+                edges: &[WeightedEdge],
+                root: NodeLabel,
+            ) -> Vec<usize> {
+                // Cheapest edge into every node but the root
+                let mut cheapest: Vec<Option<usize>> = vec![None; num_nodes];
+                for (index, (source, target, weight)) in edges.iter().enumerate() {
+                    if source == target || *target == root {
+                        continue;
+                    }
+                    match cheapest[*target] {
+                        Some(best) if edges[best].2 <= *weight => {}
+                        _ => cheapest[*target] = Some(index),
+                    }
+                }
+                let cheapest_source = |node: NodeLabel| cheapest[node].map_or(root, |i| edges[i].0);
+
+                // Follow cheapest edges backwards from every node to find the cycles they form
+                let mut component: Vec<Option<usize>> = vec![None; num_nodes];
+                let mut visited_by = vec![None; num_nodes];
+                let mut in_cycle = vec![false; num_nodes];
+                let mut num_components = 0;
+
+                for start in 0..num_nodes {
+                    let mut node = start;
+                    while node != root && visited_by[node].is_none() {
+                        visited_by[node] = Some(start);
+                        node = cheapest_source(node);
+                    }
+                    if node == root || visited_by[node] != Some(start) || component[node].is_some()
+                    {
+                        continue;
+                    }
+
+                    let mut cycle_node = node;
+                    loop {
+                        component[cycle_node] = Some(num_components);
+                        in_cycle[cycle_node] = true;
+                        cycle_node = cheapest_source(cycle_node);
+                        if cycle_node == node {
+                            break;
+                        }
+                    }
+                    num_components += 1;
+                }
+
+                if num_components == 0 {
+                    return cheapest.into_iter().flatten().collect();
+                }
+
+                // Contract each cycle into one node. Entering a cycle at a node means giving
+                // up that node's cheapest edge, so those edges get cheaper by that much.
+                let component: Vec<usize> = component
+                    .into_iter()
+                    .map(|c| {
+                        c.unwrap_or_else(|| {
+                            num_components += 1;
+                            num_components - 1
+                        })
+                    })
+                    .collect();
+
+                let mut contracted = Vec::new();
+                let mut origin = Vec::new();
+                for (index, (source, target, weight)) in edges.iter().enumerate() {
+                    if component[*source] == component[*target] {
+                        continue;
+                    }
+                    let weight = match cheapest[*target] {
+                        Some(best) if in_cycle[*target] => weight - edges[best].2,
+                        _ => *weight,
+                    };
+                    contracted.push((component[*source], component[*target], weight));
+                    origin.push(index);
+                }
+
+                let mut chosen: Vec<usize> =
+                    chu_liu_edmonds(num_components, &contracted, component[root])
+                        .into_iter()
+                        .map(|index| origin[index])
+                        .collect();
+
+                // Every cycle node keeps its cheapest edge, except the one the cycle is entered by
+                let mut entered = vec![false; num_nodes];
+                for index in chosen.iter() {
+                    entered[edges[*index].1] = true;
+                }
+                for node in 0..num_nodes {
+                    if in_cycle[node] && !entered[node] {
+                        chosen.extend(cheapest[node]);
+                    }
+                }
+                chosen
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use crate::random::Rng;
+
+                // Try every choice of incoming edge per node and keep the cheapest that reaches the root
+                fn brute_force(graph: &Graph<i32>, root: NodeLabel) -> Option<i64> {
+                    let n = graph.num_nodes();
+                    let incoming: Vec<Vec<(NodeLabel, i64)>> = (0..n)
+                        .map(|target| {
+                            (0..n)
+                                .filter(|source| *source != target)
+                                .filter_map(|source| {
+                                    graph.edges[source]
+                                        .get(&target)
+                                        .map(|w| (source, i64::from(*w)))
+                                })
+                                .collect()
+                        })
+                        .collect();
+
+                    let mut best = None;
+                    let mut choice = vec![0; n];
+                    loop {
+                        let valid = (0..n).all(|node| node == root || !incoming[node].is_empty())
+                            && (0..n).all(|node| {
+                                let mut current = node;
+                                for _ in 0..n {
+                                    if current == root {
+                                        return true;
+                                    }
+                                    current = incoming[current][choice[current]].0;
+                                }
+                                current == root
+                            });
+                        if valid {
+                            let weight: i64 = (0..n)
+                                .filter(|node| *node != root)
+                                .map(|node| incoming[node][choice[node]].1)
+                                .sum();
+                            best = Some(best.map_or(weight, |b: i64| b.min(weight)));
+                        }
+
+                        // Next combination
+                        let mut node = 0;
+                        loop {
+                            if node == n {
+                                return best;
+                            }
+                            if node != root && choice[node] + 1 < incoming[node].len() {
+                                choice[node] += 1;
+                                break;
+                            }
+                            choice[node] = 0;
+                            node += 1;
+                        }
+                    }
+                }
+
+                #[test]
+                fn test_min_arborescence() {
+                    // 1 and 2 prefer each other, so the cycle has to be broken
+                    let graph = Graph::new(
+                        vec![0; 3],
+                        vec![(0, 1, 10), (0, 2, 12), (1, 2, 1), (2, 1, 1)],
+                    );
+                    assert_eq!(
+                        graph.min_arborescence(0),
+                        Ok(Arborescence {
+                            parents: vec![None, Some(0), Some(1)],
+                            weight: 11,
+                        })
+                    );
+                }
+
+                #[test]
+                fn test_min_arborescence_errors() {
+                    let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 1), (2, 3)]);
+                    assert_eq!(
+                        graph.min_arborescence(0),
+                        Err(ArborescenceError::Unreachable(vec![2, 3]))
+                    );
+                    assert_eq!(
+                        graph.min_arborescence(4),
+                        Err(ArborescenceError::RootOutOfRange(4))
+                    );
+                }
+
+                #[test]
+                fn test_min_arborescence_random() {
+                    let mut rng = Rng::new(32);
+                    for _ in 0..200 {
+                        let n = 2 + rng.below(4);
+                        let mut edges = Vec::new();
+                        for source in 0..n {
+                            for target in 0..n {
+                                if source != target && rng.next_f64() < 0.6 {
+                                    edges.push((source, target, rng.below(20) as i32));
+                                }
+                            }
+                        }
+                        let graph = Graph::new(vec![0; n], edges);
+                        match graph.min_arborescence(0) {
+                            Ok(arborescence) => {
+                                assert_eq!(Some(arborescence.weight), brute_force(&graph, 0));
+                                let total: i64 = (1..n)
+                                    .map(|node| {
+                                        let parent = arborescence.parents[node].unwrap();
+                                        i64::from(graph.edges[parent][&node])
+                                    })
+                                    .sum();
+                                assert_eq!(total, arborescence.weight);
+                            }
+                            Err(_) => assert_eq!(brute_force(&graph, 0), None),
+                        }
+                    }
+                }
+            }
+        }
+
+        // Dominators of a rooted flow graph: a dominates b when every path from the root
+        // to b goes through a. Uses Cooper, Harvey and Kennedy's iterative algorithm.
+        pub mod dominators {
+            use super::{Graph, NodeLabel};
+            use std::collections::BTreeSet;
+            use std::error::Error;
+            use std::fmt;
+
+            #[derive(Debug)]
+            pub struct DominatorTree {
+                root: NodeLabel,
+                // None for the root and for nodes the root can't reach
+                idom: Vec<Option<NodeLabel>>,
+                children: Vec<Vec<NodeLabel>>,
+                frontier: Vec<Vec<NodeLabel>>,
+                // Entry and exit times of a walk over the dominator tree
+                entry: Vec<usize>,
+                exit: Vec<usize>,
+            }
+
+            #[derive(Debug, PartialEq)]
+            pub enum DominatorError {
+                RootOutOfRange(NodeLabel),
+            }
+
+            impl fmt::Display for DominatorError {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        DominatorError::RootOutOfRange(root) => {
+                            write!(f, "root {} is not a node in the graph", root)
+                        }
+                    }
+                }
+            }
+
+            impl Error for DominatorError {}
+
+            impl DominatorTree {
+                pub fn root(&self) -> NodeLabel {
+                    self.root
+                }
+
+                pub fn is_reachable(&self, node: NodeLabel) -> bool {
+                    node == self.root || self.idom[node].is_some()
+                }
+
+                pub fn immediate_dominator(&self, node: NodeLabel) -> Option<NodeLabel> {
+                    self.idom[node]
+                }
+
+                pub fn immediate_dominators(&self) -> &[Option<NodeLabel>] {
+                    &self.idom
+                }
+
+                // Nodes immediately dominated by node
+                pub fn children(&self, node: NodeLabel) -> &[NodeLabel] {
+                    &self.children[node]
+                }
+
+                pub fn dominance_frontier(&self, node: NodeLabel) -> &[NodeLabel] {
+                    &self.frontier[node]
+                }
+
+                // Every node dominates itself
+                pub fn dominates(&self, dominator: NodeLabel, node: NodeLabel) -> bool {
+                    self.is_reachable(dominator)
+                        && self.is_reachable(node)
+                        && self.entry[dominator] <= self.entry[node]
+                        && self.exit[node] <= self.exit[dominator]
+                }
+
+                // node, its immediate dominator, and so on up to the root
+                pub fn dominators_of(&self, node: NodeLabel) -> Vec<NodeLabel> {
+                    if !self.is_reachable(node) {
+                        return Vec::new();
+                    }
+                    let mut chain = vec![node];
+                    let mut current = node;
+                    while let Some(parent) = self.idom[current] {
+                        chain.push(parent);
+                        current = parent;
+                    }
+                    chain
+                }
+            }
+
+            impl<T> Graph<T> {
+                pub fn dominators(&self, root: NodeLabel) -> Result<DominatorTree, DominatorError> {
+                    let num_nodes = self.nodes.len();
+                    if root >= num_nodes {
+                        return Err(DominatorError::RootOutOfRange(root));
+                    }
+                    let postorder = self.postorder(root);
+                    let mut order = vec![None; num_nodes];
+                    for (index, node) in postorder.iter().enumerate() {
+                        order[*node] = Some(index);
+                    }
+
+                    let mut predecessors = vec![Vec::new(); num_nodes];
+                    for (node, edges) in self.edges.iter().enumerate() {
+                        if order[node].is_none() {
+                            continue;
+                        }
+                        for edge_node in edges.keys() {
+                            predecessors[*edge_node].push(node);
+                        }
+                    }
+
+                    // The root temporarily dominates itself so intersections have somewhere to stop
+                    let mut idom = vec![None; num_nodes];
+                    idom[root] = Some(root);
+                    let mut changed = true;
+                    while changed {
+                        changed = false;
+                        for node in postorder.iter().rev().filter(|n| **n != root) {
+                            let mut processed =
+                                predecessors[*node].iter().filter(|p| idom[**p].is_some());
+                            let mut new_idom = match processed.next() {
+                                Some(first) => *first,
+                                None => continue,
+                            };
+                            for predecessor in processed {
+                                new_idom = intersect(&idom, &order, *predecessor, new_idom);
+                            }
+                            if idom[*node] != Some(new_idom) {
+                                idom[*node] = Some(new_idom);
+                                changed = true;
+                            }
+                        }
+                    }
+                    idom[root] = None;
+
+                    let mut children = vec![Vec::new(); num_nodes];
+                    for (node, parent) in idom.iter().enumerate() {
+                        if let Some(parent) = parent {
+                            children[*parent].push(node);
+                        }
+                    }
+
+                    // A join point is in the frontier of everything between its predecessors
+                    // and its immediate dominator
+                    let mut frontier = vec![BTreeSet::new(); num_nodes];
+                    for node in postorder.iter() {
+                        if predecessors[*node].len() < 2 {
+                            continue;
+                        }
+                        for predecessor in predecessors[*node].iter() {
+                            let mut runner = Some(*predecessor);
+                            while let Some(current) = runner {
+                                if Some(current) == idom[*node] {
+                                    break;
+                                }
+                                frontier[current].insert(*node);
+                                runner = idom[current];
+                            }
+                        }
+                    }
+
+                    let mut entry = vec![0; num_nodes];
+                    let mut exit = vec![0; num_nodes];
+                    let mut clock = 0;
+                    let mut stack = vec![(root, false)];
+                    while let Some((node, finished)) = stack.pop() {
+                        clock += 1;
+                        if finished {
+                            exit[node] = clock;
+                            continue;
+                        }
+                        entry[node] = clock;
+                        stack.push((node, true));
+                        for child in children[node].iter() {
+                            stack.push((*child, false));
+                        }
+                    }
+
+                    Ok(DominatorTree {
+                        root,
+                        idom,
+                        children,
+                        frontier: frontier
+                            .into_iter()
+                            .map(|f| f.into_iter().collect())
+                            .collect(),
+                        entry,
+                        exit,
+                    })
+                }
+
+                // Depth first postorder of the nodes reachable from root
+                fn postorder(&self, root: NodeLabel) -> Vec<NodeLabel> {
+                    let mut seen = vec![false; self.nodes.len()];
+                    let mut postorder = Vec::new();
+                    let mut stack = vec![(root, self.edges[root].keys())];
+                    seen[root] = true;
+
+                    while let Some((node, edges)) = stack.last_mut() {
+                        match edges.next() {
+                            Some(edge_node) if !seen[*edge_node] => {
+                                seen[*edge_node] = true;
+                                stack.push((*edge_node, self.edges[*edge_node].keys()));
+                            }
+                            Some(_) => {}
+                            None => {
+                                postorder.push(*node);
+                                stack.pop();
+                            }
+                        }
+                    }
+                    postorder
+                }
+            }
+
+            // Walk both fingers up the tree until they meet, always moving the one that is
+            // earlier in postorder
+            fn intersect(
+                idom: &[Option<NodeLabel>],
+                order: &[Option<usize>],
+                mut a: NodeLabel,
+                mut b: NodeLabel,
+            ) -> NodeLabel {
+                while a != b {
+                    while order[a] < order[b] {
+                        a = idom[a].unwrap_or(a);
+                    }
+                    while order[b] < order[a] {
+                        b = idom[b].unwrap_or(b);
+                    }
+                }
+                a
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                fn loop_graph() -> Graph<i32> {
+                    // 0 -> 1, 1 branches to 2 and 3, which join at 4, which loops back to 1
+                    // and exits to 5. Node 6 is unreachable.
+                    Graph::new_unweighted(
+                        vec![0; 7],
+                        vec![
+                            (0, 1),
+                            (1, 2),
+                            (1, 3),
+                            (2, 4),
+                            (3, 4),
+                            (4, 1),
+                            (4, 5),
+                            (6, 5),
+                        ],
+                    )
+                }
+
+                #[test]
+                fn test_immediate_dominators() {
+                    let tree = loop_graph().dominators(0).unwrap();
+                    assert_eq!(
+                        tree.immediate_dominators(),
+                        &[None, Some(0), Some(1), Some(1), Some(1), Some(4), None]
+                    );
+                    assert_eq!(tree.children(1), &[2, 3, 4]);
+                    assert!(!tree.is_reachable(6));
+
+                    assert_eq!(
+                        loop_graph().dominators(7).unwrap_err(),
+                        DominatorError::RootOutOfRange(7)
+                    );
+                }
+
+                #[test]
+                fn test_dominance_frontier() {
+                    let tree = loop_graph().dominators(0).unwrap();
+                    assert_eq!(tree.dominance_frontier(0), &[] as &[NodeLabel]);
+                    assert_eq!(tree.dominance_frontier(1), &[1]);
+                    assert_eq!(tree.dominance_frontier(2), &[4]);
+                    assert_eq!(tree.dominance_frontier(3), &[4]);
+                    assert_eq!(tree.dominance_frontier(4), &[1]);
+                }
+
+                #[test]
+                fn test_dominates() {
+                    let tree = loop_graph().dominators(0).unwrap();
+                    assert!(tree.dominates(1, 5));
+                    assert!(tree.dominates(4, 4));
+                    assert!(!tree.dominates(2, 4));
+                    assert!(!tree.dominates(6, 5));
+                    assert_eq!(tree.dominators_of(5), vec![5, 4, 1, 0]);
+                    assert_eq!(tree.dominators_of(6), Vec::<NodeLabel>::new());
+                }
+            }
+        }
+
+        // Rooted trees built from the undirected view of a graph
+        pub mod tree {
+            use super::{Graph, NodeLabel};
+            use std::collections::{HashMap, VecDeque};
+            use std::error::Error;
+            use std::fmt;
+
+            #[derive(Debug, PartialEq)]
+            pub enum TreeError {
+                Empty,
+                RootOutOfRange(NodeLabel),
+                SelfLoop(NodeLabel),
+                // The nodes around one cycle, in order
+                Cycle(Vec<NodeLabel>),
+                // Nodes that can't be reached from the root
+                Disconnected(Vec<NodeLabel>),
+            }
+
+            impl fmt::Display for TreeError {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        TreeError::Empty => write!(f, "a tree needs at least one node"),
+                        TreeError::RootOutOfRange(root) => {
+                            write!(f, "root {} is not a node in the graph", root)
+                        }
+                        TreeError::SelfLoop(node) => write!(f, "node {} has a self loop", node),
+                        TreeError::Cycle(nodes) => write!(f, "nodes {:?} form a cycle", nodes),
+                        TreeError::Disconnected(nodes) => {
+                            write!(f, "nodes {:?} are not connected to the root", nodes)
+                        }
+                    }
+                }
+            }
+
+            impl Error for TreeError {}
+
+            #[derive(Debug)]
+            pub struct RootedTree {
+                root: NodeLabel,
+                parents: Vec<Option<NodeLabel>>,
+                // Weight of the edge up to the parent, 0 for the root
+                parent_weights: Vec<i64>,
+                depths: Vec<usize>,
+                children: Vec<Vec<NodeLabel>>,
+                // Parents always come before their children
+                preorder: Vec<NodeLabel>,
+            }
+
+            impl RootedTree {
+                // Edge direction is ignored, so parent -> child edges, child -> parent edges
+                // and edges both ways all describe the same tree
+                pub fn from_graph<T>(
+                    graph: &Graph<T>,
+                    root: NodeLabel,
+                ) -> Result<RootedTree, TreeError> {
+                    let num_nodes = graph.nodes.len();
+                    if num_nodes == 0 {
+                        return Err(TreeError::Empty);
+                    }
+                    if root >= num_nodes {
+                        return Err(TreeError::RootOutOfRange(root));
+                    }
+                    if let Some(node) = (0..num_nodes).find(|n| graph.edges[*n].contains_key(n)) {
+                        return Err(TreeError::SelfLoop(node));
+                    }
+
+                    let undirected = graph.undirected_edges();
+                    let mut parents = vec![None; num_nodes];
+                    let mut parent_weights = vec![0; num_nodes];
+                    let mut depths = vec![0; num_nodes];
+                    let mut children = vec![Vec::new(); num_nodes];
+                    let mut seen = vec![false; num_nodes];
+                    let mut preorder = Vec::with_capacity(num_nodes);
+                    let mut queue = VecDeque::new();
+
+                    seen[root] = true;
+                    queue.push_back(root);
+                    while let Some(node) = queue.pop_front() {
+                        preorder.push(node);
+                        for (edge_node, weight) in undirected[node].iter() {
+                            if parents[node] == Some(*edge_node) {
+                                continue;
+                            }
+                            if seen[*edge_node] {
+                                return Err(TreeError::Cycle(cycle(&parents, node, *edge_node)));
+                            }
+                            seen[*edge_node] = true;
+                            parents[*edge_node] = Some(node);
+                            parent_weights[*edge_node] = i64::from(*weight);
+                            depths[*edge_node] = depths[node] + 1;
+                            children[node].push(*edge_node);
+                            queue.push_back(*edge_node);
+                        }
+                    }
+
+                    let unreachable: Vec<NodeLabel> =
+                        (0..num_nodes).filter(|n| !seen[*n]).collect();
+                    if !unreachable.is_empty() {
+                        return Err(TreeError::Disconnected(unreachable));
+                    }
+
+                    Ok(RootedTree {
+                        root,
+                        parents,
+                        parent_weights,
+                        depths,
+                        children,
+                        preorder,
+                    })
+                }
+
+                pub fn root(&self) -> NodeLabel {
+                    self.root
+                }
+
+                pub fn num_nodes(&self) -> usize {
+                    self.parents.len()
+                }
+
+                pub fn parent(&self, node: NodeLabel) -> Option<NodeLabel> {
+                    self.parents[node]
+                }
+
+                pub fn parents(&self) -> &[Option<NodeLabel>] {
+                    &self.parents
+                }
+
+                pub fn depth(&self, node: NodeLabel) -> usize {
+                    self.depths[node]
+                }
+
+                pub fn depths(&self) -> &[usize] {
+                    &self.depths
+                }
+
+                pub fn children(&self, node: NodeLabel) -> &[NodeLabel] {
+                    &self.children[node]
+                }
+
+                pub fn preorder(&self) -> &[NodeLabel] {
+                    &self.preorder
+                }
+
+                // Tree DP from the leaves up: combine gets a node and its children's values
+                pub fn fold_up<V, F>(&self, mut combine: F) -> Vec<V>
+                where
+                    F: FnMut(NodeLabel, &[&V]) -> V,
+                {
+                    let mut values: Vec<Option<V>> = (0..self.num_nodes()).map(|_| None).collect();
+                    for node in self.preorder.iter().rev() {
+                        let value = {
+                            let child_values: Vec<&V> = self.children[*node]
+                                .iter()
+                                .filter_map(|child| values[*child].as_ref())
+                                .collect();
+                            combine(*node, &child_values)
+                        };
+                        values[*node] = Some(value);
+                    }
+                    values.into_iter().flatten().collect()
+                }
+
+                // Tree DP from the root down: extend gets a node and its parent's value
+                pub fn fold_down<V, F>(&self, mut extend: F) -> Vec<V>
+                where
+                    F: FnMut(NodeLabel, Option<&V>) -> V,
+                {
+                    let mut values: Vec<Option<V>> = (0..self.num_nodes()).map(|_| None).collect();
+                    for node in self.preorder.iter() {
+                        let value = {
+                            let parent_value = self.parents[*node].and_then(|p| values[p].as_ref());
+                            extend(*node, parent_value)
+                        };
+                        values[*node] = Some(value);
+                    }
+                    values.into_iter().flatten().collect()
+                }
+
+                pub fn subtree_sizes(&self) -> Vec<usize> {
+                    self.fold_up(|_node, children| {
+                        1 + children.iter().map(|size| **size).sum::<usize>()
+                    })
+                }
+
+                // Longest path by edge weight, found by two farthest-node searches.
+                // Weights should be non-negative.
+                pub fn diameter(&self) -> (i64, Vec<NodeLabel>) {
+                    self.longest_path(|node, neighbour| self.weight_between(node, neighbour))
+                }
+
+                // Longest path by number of edges, whatever the weights
+                pub fn diameter_edges(&self) -> (usize, Vec<NodeLabel>) {
+                    let (_distance, path) = self.longest_path(|_node, _neighbour| 1);
+                    (path.len() - 1, path)
+                }
+
+                fn longest_path<F>(&self, weight: F) -> (i64, Vec<NodeLabel>)
+                where
+                    F: Fn(NodeLabel, NodeLabel) -> i64,
+                {
+                    let (start, _distance, _previous) = self.farthest_from(self.root, &weight);
+                    let (end, distance, previous) = self.farthest_from(start, &weight);
+
+                    let mut path = vec![end];
+                    let mut current = end;
+                    while let Some(node) = previous[current] {
+                        path.push(node);
+                        current = node;
+                    }
+                    (distance, path)
+                }
+
+                // Every level's centroid splits what's left into pieces of at most half the size.
+                // Returns each node's parent in the centroid tree, None for the top centroid.
+                pub fn centroid_decomposition(&self) -> Vec<Option<NodeLabel>> {
+                    let num_nodes = self.num_nodes();
+                    let mut removed = vec![false; num_nodes];
+                    let mut centroid_parents = vec![None; num_nodes];
+                    let mut pieces = vec![(self.root, None)];
+
+                    while let Some((start, parent_centroid)) = pieces.pop() {
+                        // Walk the piece from start, remembering where each node was reached from
+                        let mut order = vec![start];
+                        let mut reached_from = vec![None];
+                        let mut index = 0;
+                        while index < order.len() {
+                            let node = order[index];
+                            for neighbour in self.neighbours(node) {
+                                if Some(neighbour) != reached_from[index] && !removed[neighbour] {
+                                    order.push(neighbour);
+                                    reached_from.push(Some(node));
+                                }
+                            }
+                            index += 1;
+                        }
+
+                        let position: HashMap<NodeLabel, usize> =
+                            order.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+                        let mut sizes = vec![1; order.len()];
+                        for index in (1..order.len()).rev() {
+                            if let Some(parent) = reached_from[index] {
+                                sizes[position[&parent]] += sizes[index];
+                            }
+                        }
+
+                        // Walk towards the heavy side until no piece is bigger than half
+                        let total = order.len();
+                        let mut centroid = start;
+                        while let Some(next) = self.neighbours(centroid).find(|n| {
+                            !removed[*n]
+                                && reached_from[position[n]] == Some(centroid)
+                                && sizes[position[n]] * 2 > total
+                        }) {
+                            centroid = next;
+                        }
+
+                        removed[centroid] = true;
+                        centroid_parents[centroid] = parent_centroid;
+                        for neighbour in self.neighbours(centroid) {
+                            if !removed[neighbour] {
+                                pieces.push((neighbour, Some(centroid)));
+                            }
+                        }
+                    }
+                    centroid_parents
+                }
+
+                fn neighbours(&self, node: NodeLabel) -> impl Iterator<Item = NodeLabel> + '_ {
+                    self.children[node]
+                        .iter()
+                        .cloned()
+                        .chain(self.parents[node])
+                }
+
+                fn weight_between(&self, a: NodeLabel, b: NodeLabel) -> i64 {
+                    if self.parents[a] == Some(b) {
+                        self.parent_weights[a]
+                    } else {
+                        self.parent_weights[b]
+                    }
+                }
+
+                fn farthest_from<F>(
+                    &self,
+                    start: NodeLabel,
+                    weight: &F,
+                ) -> (NodeLabel, i64, Vec<Option<NodeLabel>>)
+                where
+                    F: Fn(NodeLabel, NodeLabel) -> i64,
+                {
+                    let mut distances = vec![None; self.num_nodes()];
+                    let mut previous = vec![None; self.num_nodes()];
+                    let mut stack = vec![start];
+                    distances[start] = Some(0);
+
+                    while let Some(node) = stack.pop() {
+                        let distance = distances[node].unwrap_or(0);
+                        for neighbour in self.neighbours(node) {
+                            if distances[neighbour].is_none() {
+                                distances[neighbour] = Some(distance + weight(node, neighbour));
+                                previous[neighbour] = Some(node);
+                                stack.push(neighbour);
+                            }
+                        }
+                    }
+
+                    let (farthest, distance) = distances
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(node, distance)| distance.map(|d| (node, d)))
+                        .max_by_key(|(node, distance)| (*distance, std::cmp::Reverse(*node)))
+                        .unwrap_or((start, 0));
+                    (farthest, distance, previous)
+                }
+            }
+
+            // Lowest common ancestors by jumping up in powers of two
+            #[derive(Debug)]
+            pub struct BinaryLifting {
+                // ancestors[k][node] is the 2^k-th ancestor, or the root if that's too far
+                ancestors: Vec<Vec<NodeLabel>>,
+                depths: Vec<usize>,
+            }
+
+            impl BinaryLifting {
+                pub fn new(tree: &RootedTree) -> BinaryLifting {
+                    let num_nodes = tree.num_nodes();
+                    let mut levels = 1;
+                    while (1 << levels) < num_nodes {
+                        levels += 1;
+                    }
+
+                    let first: Vec<NodeLabel> = (0..num_nodes)
+                        .map(|n| tree.parents[n].unwrap_or(tree.root))
+                        .collect();
+                    let mut ancestors = vec![first];
+                    for level in 1..levels {
+                        let previous = &ancestors[level - 1];
+                        let next = (0..num_nodes).map(|n| previous[previous[n]]).collect();
+                        ancestors.push(next);
+                    }
+
+                    BinaryLifting {
+                        ancestors,
+                        depths: tree.depths.clone(),
+                    }
+                }
+
+                // None when node is less than k deep
+                pub fn kth_ancestor(&self, node: NodeLabel, k: usize) -> Option<NodeLabel> {
+                    if k > self.depths[node] {
+                        return None;
+                    }
+                    let mut current = node;
+                    for (level, ancestors) in self.ancestors.iter().enumerate() {
+                        if k & (1 << level) != 0 {
+                            current = ancestors[current];
+                        }
+                    }
+                    Some(current)
+                }
+
+                pub fn lca(&self, u: NodeLabel, v: NodeLabel) -> NodeLabel {
+                    let (mut u, mut v) = if self.depths[u] < self.depths[v] {
+                        (v, u)
+                    } else {
+                        (u, v)
+                    };
+                    u = self
+                        .kth_ancestor(u, self.depths[u] - self.depths[v])
+                        .unwrap_or(u);
+                    if u == v {
+                        return u;
+                    }
+                    for ancestors in self.ancestors.iter().rev() {
+                        if ancestors[u] != ancestors[v] {
+                            u = ancestors[u];
+                            v = ancestors[v];
+                        }
+                    }
+                    self.ancestors[0][u]
+                }
+            }
+
+            // Lowest common ancestors as the shallowest node between two first visits of an
+            // Euler tour, answered in O(1) from a sparse table
+            #[derive(Debug)]
+            pub struct EulerTourLca {
+                tour: Vec<NodeLabel>,
+                first_visit: Vec<usize>,
+                // sparse[k][i] is the shallowest node in tour[i..i + 2^k]
+                sparse: Vec<Vec<NodeLabel>>,
+                depths: Vec<usize>,
+            }
+
+            impl EulerTourLca {
+                pub fn new(tree: &RootedTree) -> EulerTourLca {
+                    let num_nodes = tree.num_nodes();
+                    let mut tour = Vec::with_capacity(2 * num_nodes);
+                    let mut first_visit = vec![0; num_nodes];
+                    let mut stack = vec![(tree.root, 0)];
+
+                    while let Some((node, next_child)) = stack.pop() {
+                        if next_child == 0 {
+                            first_visit[node] = tour.len();
+                        }
+                        tour.push(node);
+                        if let Some(child) = tree.children[node].get(next_child) {
+                            stack.push((node, next_child + 1));
+                            stack.push((*child, 0));
+                        }
+                    }
+
+                    let shallower = |a: NodeLabel, b: NodeLabel| {
+                        if tree.depths[a] <= tree.depths[b] {
+                            a
+                        } else {
+                            b
+                        }
+                    };
+                    let mut sparse = vec![tour.clone()];
+                    let mut width = 1;
+                    while 2 * width <= tour.len() {
+                        let previous = &sparse[sparse.len() - 1];
+                        let next = (0..=tour.len() - 2 * width)
+                            .map(|i| shallower(previous[i], previous[i + width]))
+                            .collect();
+                        sparse.push(next);
+                        width *= 2;
+                    }
+
+                    EulerTourLca {
+                        tour,
+                        first_visit,
+                        sparse,
+                        depths: tree.depths.clone(),
+                    }
+                }
+
+                pub fn tour(&self) -> &[NodeLabel] {
+                    &self.tour
+                }
+
+                pub fn lca(&self, u: NodeLabel, v: NodeLabel) -> NodeLabel {
+                    let (mut left, mut right) = (self.first_visit[u], self.first_visit[v]);
+                    if left > right {
+                        std::mem::swap(&mut left, &mut right);
+                    }
+                    let length = right - left + 1;
+                    let level = (usize::BITS - 1 - length.leading_zeros()) as usize;
+                    let a = self.sparse[level][left];
+                    let b = self.sparse[level][right + 1 - (1 << level)];
+                    if self.depths[a] <= self.depths[b] {
+                        a
+                    } else {
+                        b
+                    }
+                }
+            }
+
+            // Nodes around the cycle closed by the edge from node to edge_node
+            fn cycle(
+                parents: &[Option<NodeLabel>],
+                node: NodeLabel,
+                edge_node: NodeLabel,
+            ) -> Vec<NodeLabel> {
+                let ancestors = |start: NodeLabel| {
+                    let mut chain = vec![start];
+                    let mut current = start;
+                    while let Some(parent) = parents[current] {
+                        chain.push(parent);
+                        current = parent;
+                    }
+                    chain
+                };
+                let mut up = ancestors(node);
+                let mut down = ancestors(edge_node);
+
+                // Drop the shared part above the meeting point, but keep the meeting point itself
+                while up.len() > 1 && down.len() > 1 && up[up.len() - 2] == down[down.len() - 2] {
+                    up.pop();
+                    down.pop();
+                }
+                down.pop();
+                down.reverse();
+                up.extend(down);
+                up
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                //        0
+                //      / | \
+                //     1  2  3
+                //    / \     \
+                //   4   5     6
+                //             |
+                //             7
+                fn sample() -> RootedTree {
+                    let graph = Graph::new_unweighted(
+                        vec![0; 8],
+                        vec![(0, 1), (0, 2), (0, 3), (1, 4), (5, 1), (3, 6), (6, 7)],
+                    );
+                    RootedTree::from_graph(&graph, 0).unwrap()
+                }
+
+                #[test]
+                fn test_from_graph() {
+                    let tree = sample();
+                    assert_eq!(
+                        tree.parents(),
+                        &[
+                            None,
+                            Some(0),
+                            Some(0),
+                            Some(0),
+                            Some(1),
+                            Some(1),
+                            Some(3),
+                            Some(6)
+                        ]
+                    );
+                    assert_eq!(tree.depths(), &[0, 1, 1, 1, 2, 2, 2, 3]);
+                    assert_eq!(tree.subtree_sizes(), vec![8, 3, 1, 3, 1, 1, 2, 1]);
+                }
+
+                #[test]
+                fn test_from_graph_errors() {
+                    let graph: Graph<i32> = Graph::new(vec![], vec![]);
+                    assert_eq!(
+                        RootedTree::from_graph(&graph, 0).unwrap_err(),
+                        TreeError::Empty
+                    );
+
+                    let graph =
+                        Graph::new_unweighted(vec![0; 4], vec![(0, 1), (1, 2), (2, 3), (3, 1)]);
+                    assert_eq!(
+                        RootedTree::from_graph(&graph, 0).unwrap_err(),
+                        TreeError::Cycle(vec![2, 1, 3])
+                    );
+
+                    let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 1), (2, 3)]);
+                    assert_eq!(
+                        RootedTree::from_graph(&graph, 0).unwrap_err(),
+                        TreeError::Disconnected(vec![2, 3])
+                    );
+
+                    let graph = Graph::new_unweighted(vec![0; 2], vec![(0, 1), (1, 1)]);
+                    assert_eq!(
+                        RootedTree::from_graph(&graph, 0).unwrap_err(),
+                        TreeError::SelfLoop(1)
+                    );
+                    assert_eq!(
+                        RootedTree::from_graph(&graph, 2).unwrap_err(),
+                        TreeError::RootOutOfRange(2)
+                    );
+                }
+
+                #[test]
+                fn test_lca() {
+                    let tree = sample();
+                    let lifting = BinaryLifting::new(&tree);
+                    let euler = EulerTourLca::new(&tree);
+                    let expected = [(4, 5, 1), (4, 7, 0), (6, 7, 6), (2, 2, 2), (5, 1, 1)];
+                    for (u, v, lca) in expected.iter() {
+                        assert_eq!(lifting.lca(*u, *v), *lca);
+                        assert_eq!(euler.lca(*u, *v), *lca);
+                    }
+                    assert_eq!(lifting.kth_ancestor(7, 2), Some(3));
+                    assert_eq!(lifting.kth_ancestor(7, 4), None);
+                    assert_eq!(euler.tour().len(), 15);
+                }
+
+                #[test]
+                fn test_diameter() {
+                    let tree = sample();
+                    let (length, path) = tree.diameter();
+                    assert_eq!(length, 5);
+                    assert_eq!(path.len(), 6);
+
+                    let graph = Graph::new(vec![0; 4], vec![(0, 1, 1), (0, 2, 10), (0, 3, 4)]);
+                    let tree = RootedTree::from_graph(&graph, 0).unwrap();
+                    assert_eq!(tree.diameter(), (14, vec![3, 0, 2]));
+                    assert_eq!(tree.diameter_edges(), (2, vec![2, 0, 1]));
+
+                    // Weightless edges still have a length in edges
+                    let graph = Graph::new(vec![0; 4], vec![(0, 1, 0), (1, 2, 0), (1, 3, 0)]);
+                    let tree = RootedTree::from_graph(&graph, 0).unwrap();
+                    assert_eq!(tree.diameter().0, 0);
+                    assert_eq!(tree.diameter_edges().0, 2);
+
+                    let graph =
+                        Graph::new_unweighted(vec![0; 5], vec![(0, 1), (1, 2), (2, 3), (1, 4)]);
+                    let tree = RootedTree::from_graph(&graph, 0).unwrap();
+                    assert_eq!(tree.diameter(), (3, vec![0, 1, 2, 3]));
+                    assert_eq!(tree.diameter_edges(), (3, vec![0, 1, 2, 3]));
+                }
+
+                #[test]
+                fn test_centroid_decomposition() {
+                    // Path 0-1-2-3-4-5-6: centroid 3, then 1 and 5
+                    let edges = (0..6).map(|i| (i, i + 1)).collect();
+                    let graph = Graph::new_unweighted(vec![0; 7], edges);
+                    let tree = RootedTree::from_graph(&graph, 0).unwrap();
+                    assert_eq!(
+                        tree.centroid_decomposition(),
+                        vec![Some(1), Some(3), Some(1), None, Some(5), Some(3), Some(5)]
+                    );
+                }
+
+                #[test]
+                fn test_fold_down() {
+                    let tree = sample();
+                    let depths =
+                        tree.fold_down(|_node, parent: Option<&usize>| parent.map_or(0, |d| d + 1));
+                    assert_eq!(depths, tree.depths().to_vec());
+                }
+            }
+        }
+
+        // Strongly connected components with Tarjan's algorithm
+        pub mod scc {
+            use super::{Graph, NodeLabel};
+
+            impl<T> Graph<T> {
+                // Components come out in reverse topological order: no edges leave the first one
+                // for a later one. Nodes inside a component are sorted.
+                pub fn strongly_connected_components(&self) -> Vec<Vec<NodeLabel>> {
+                    let num_nodes = self.nodes.len();
+                    let mut index = vec![None; num_nodes];
+                    let mut low_link = vec![0; num_nodes];
+                    let mut on_stack = vec![false; num_nodes];
+                    let mut stack = Vec::new();
+                    let mut components = Vec::new();
+                    let mut counter = 0;
+
+                    for start in 0..num_nodes {
+                        if index[start].is_some() {
+                            continue;
+                        }
+
+                        // Explicit call stack of (node, edges still to look at)
+                        let mut calls = vec![(start, self.edges[start].keys())];
+                        index[start] = Some(counter);
+                        low_link[start] = counter;
+                        counter += 1;
+                        stack.push(start);
+                        on_stack[start] = true;
+
+                        while let Some((node, edges)) = calls.last_mut() {
+                            let node = *node;
+                            match edges.next() {
+                                Some(edge_node) => match index[*edge_node] {
+                                    None => {
+                                        index[*edge_node] = Some(counter);
+                                        low_link[*edge_node] = counter;
+                                        counter += 1;
+                                        stack.push(*edge_node);
+                                        on_stack[*edge_node] = true;
+                                        calls.push((*edge_node, self.edges[*edge_node].keys()));
+                                    }
+                                    Some(edge_index) if on_stack[*edge_node] => {
+                                        low_link[node] = low_link[node].min(edge_index);
+                                    }
+                                    Some(_) => {}
+                                },
+                                None => {
+                                    calls.pop();
+                                    if let Some((parent, _)) = calls.last() {
+                                        low_link[*parent] = low_link[*parent].min(low_link[node]);
+                                    }
+                                    if Some(low_link[node]) != index[node] {
+                                        continue;
+                                    }
+
+                                    let mut component = Vec::new();
+                                    while let Some(member) = stack.pop() {
+                                        on_stack[member] = false;
+                                        component.push(member);
+                                        if member == node {
+                                            break;
+                                        }
+                                    }
+                                    component.sort_unstable();
+                                    components.push(component);
+                                }
+                            }
+                        }
+                    }
+                    components
+                }
+
+                // Position of each node's component in strongly_connected_components
+                pub fn component_ids(&self) -> Vec<usize> {
+                    let mut ids = vec![0; self.nodes.len()];
+                    for (id, component) in self.strongly_connected_components().iter().enumerate() {
+                        for node in component.iter() {
+                            ids[*node] = id;
+                        }
+                    }
+                    ids
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn test_strongly_connected_components() {
+                    let graph = Graph::new_unweighted(
+                        vec![0; 8],
+                        vec![
+                            (0, 1),
+                            (1, 2),
+                            (2, 0),
+                            (2, 3),
+                            (3, 4),
+                            (4, 5),
+                            (5, 3),
+                            (6, 5),
+                            (6, 7),
+                            (7, 6),
+                        ],
+                    );
+                    assert_eq!(
+                        graph.strongly_connected_components(),
+                        vec![vec![3, 4, 5], vec![0, 1, 2], vec![6, 7]]
+                    );
+                    assert_eq!(graph.component_ids(), vec![1, 1, 1, 0, 0, 0, 2, 2]);
+
+                    let graph = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 2)]);
+                    assert_eq!(
+                        graph.strongly_connected_components(),
+                        vec![vec![2], vec![1], vec![0]]
+                    );
+                }
+            }
+        }
+
+        // 2-SAT: satisfy a conjunction of two-literal clauses. Each clause (a or b) becomes
+        // the implications !a -> b and !b -> a, and the formula is unsatisfiable exactly
+        // when some variable and its negation imply each other.
+        pub mod two_sat {
+            use super::{Graph, NodeLabel};
+            use std::error::Error;
+            use std::fmt;
+
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            pub struct Literal {
+                pub variable: usize,
+                pub value: bool,
+            }
+
+            impl Literal {
+                pub fn positive(variable: usize) -> Literal {
+                    Literal {
+                        variable,
+                        value: true,
+                    }
+                }
+
+                pub fn negative(variable: usize) -> Literal {
+                    Literal {
+                        variable,
+                        value: false,
+                    }
+                }
+
+                pub fn negate(self) -> Literal {
+                    Literal {
+                        variable: self.variable,
+                        value: !self.value,
+                    }
+                }
+
+                // Node 2v is v, node 2v + 1 is !v
+                fn node(self) -> NodeLabel {
+                    2 * self.variable + if self.value { 0 } else { 1 }
+                }
+            }
+
+            // Variables whose literals imply their own negations
+            #[derive(Debug, PartialEq)]
+            pub struct Unsatisfiable {
+                pub variables: Vec<usize>,
+            }
+
+            impl fmt::Display for Unsatisfiable {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "unsatisfiable: variables {:?} conflict", self.variables)
+                }
+            }
+
+            impl Error for Unsatisfiable {}
+
+            // A clause named a variable the formula doesn't have
+            #[derive(Debug, PartialEq)]
+            pub struct VariableOutOfRange {
+                pub variable: usize,
+                pub num_variables: usize,
+            }
+
+            impl fmt::Display for VariableOutOfRange {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(
+                        f,
+                        "variable {} is out of range for {} variables",
+                        self.variable, self.num_variables
+                    )
+                }
+            }
+
+            impl Error for VariableOutOfRange {}
+
+            #[derive(Debug)]
+            pub struct TwoSat {
+                num_variables: usize,
+                clauses: Vec<(Literal, Literal)>,
+            }
+
+            impl TwoSat {
+                pub fn new(num_variables: usize) -> TwoSat {
+                    TwoSat {
+                        num_variables,
+                        clauses: Vec::new(),
+                    }
+                }
+
+                // a or b. The clause is left out if either variable is out of range.
+                pub fn add_clause(
+                    &mut self,
+                    a: Literal,
+                    b: Literal,
+                ) -> Result<(), VariableOutOfRange> {
+                    for literal in [a, b].iter() {
+                        if literal.variable >= self.num_variables {
+                            return Err(VariableOutOfRange {
+                                variable: literal.variable,
+                                num_variables: self.num_variables,
+                            });
+                        }
+                    }
+                    self.clauses.push((a, b));
+                    Ok(())
+                }
+
+                // a must hold
+                pub fn add_unit(&mut self, a: Literal) -> Result<(), VariableOutOfRange> {
+                    self.add_clause(a, a)
+                }
+
+                // a implies b
+                pub fn add_implication(
+                    &mut self,
+                    a: Literal,
+                    b: Literal,
+                ) -> Result<(), VariableOutOfRange> {
+                    self.add_clause(a.negate(), b)
+                }
+
+                // Node 2v is the literal v, node 2v + 1 is !v
+                pub fn implication_graph(&self) -> Graph<Literal> {
+                    let nodes = (0..self.num_variables)
+                        .flat_map(|v| vec![Literal::positive(v), Literal::negative(v)])
+                        .collect();
+                    let edges = self
+                        .clauses
+                        .iter()
+                        .flat_map(|(a, b)| {
+                            vec![(a.negate().node(), b.node()), (b.negate().node(), a.node())]
+                        })
+                        .collect();
+                    Graph::new_unweighted(nodes, edges)
+                }
+
+                // A value per variable, or the variables that can't be given one
+                pub fn solve(&self) -> Result<Vec<bool>, Unsatisfiable> {
+                    let component_ids = self.implication_graph().component_ids();
+
+                    let conflicting: Vec<usize> = (0..self.num_variables)
+                        .filter(|v| {
+                            component_ids[Literal::positive(*v).node()]
+                                == component_ids[Literal::negative(*v).node()]
+                        })
+                        .collect();
+                    if !conflicting.is_empty() {
+                        return Err(Unsatisfiable {
+                            variables: conflicting,
+                        });
+                    }
+
+                    // Components are in reverse topological order, so a literal whose component
+                    // comes first can't imply its negation
+                    Ok((0..self.num_variables)
+                        .map(|v| {
+                            component_ids[Literal::positive(v).node()]
+                                < component_ids[Literal::negative(v).node()]
+                        })
+                        .collect())
+                }
+
+                pub fn is_satisfied_by(&self, assignment: &[bool]) -> bool {
+                    let holds = |literal: &Literal| assignment[literal.variable] == literal.value;
+                    self.clauses.iter().all(|(a, b)| holds(a) || holds(b))
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn test_satisfiable() {
+                    // (x0 or x1) and (!x0 or x2) and (!x1 or !x2) and (!x2 or x0)
+                    let mut formula = TwoSat::new(3);
+                    formula
+                        .add_clause(Literal::positive(0), Literal::positive(1))
+                        .unwrap();
+                    formula
+                        .add_clause(Literal::negative(0), Literal::positive(2))
+                        .unwrap();
+                    formula
+                        .add_clause(Literal::negative(1), Literal::negative(2))
+                        .unwrap();
+                    formula
+                        .add_implication(Literal::positive(2), Literal::positive(0))
+                        .unwrap();
+
+                    let assignment = formula.solve().unwrap();
+                    assert!(formula.is_satisfied_by(&assignment));
+                    assert_eq!(assignment, vec![true, false, true]);
+                }
+
+                #[test]
+                fn test_unsatisfiable() {
+                    // x0 forces x1, x1 forces !x0, and x0 must hold. x2 is unconstrained.
+                    let mut formula = TwoSat::new(3);
+                    formula
+                        .add_implication(Literal::positive(0), Literal::positive(1))
+                        .unwrap();
+                    formula
+                        .add_implication(Literal::positive(1), Literal::negative(0))
+                        .unwrap();
+                    formula.add_unit(Literal::positive(0)).unwrap();
+                    assert_eq!(
+                        formula.solve(),
+                        Err(Unsatisfiable {
+                            variables: vec![0, 1]
+                        })
+                    );
+                }
+
+                #[test]
+                fn test_implication_graph() {
+                    let mut formula = TwoSat::new(2);
+                    formula
+                        .add_clause(Literal::positive(0), Literal::negative(1))
+                        .unwrap();
+                    let graph = formula.implication_graph();
+                    assert_eq!(graph.num_nodes(), 4);
+                    // !x0 -> !x1 and x1 -> x0
+                    assert_eq!(graph.bfs(1, 3), Some(vec![1, 3]));
+                    assert_eq!(graph.bfs(2, 0), Some(vec![2, 0]));
+                    assert_eq!(graph.num_edges(), 2);
+                }
+
+                #[test]
+                fn test_variable_out_of_range() {
+                    let mut formula = TwoSat::new(2);
+                    let error = formula
+                        .add_implication(Literal::positive(0), Literal::negative(2))
+                        .unwrap_err();
+                    assert_eq!(
+                        error,
+                        VariableOutOfRange {
+                            variable: 2,
+                            num_variables: 2
+                        }
+                    );
+                    assert_eq!(
+                        error.to_string(),
+                        "variable 2 is out of range for 2 variables"
+                    );
+                    assert_eq!(formula.implication_graph().num_edges(), 0);
+                }
+            }
+        }
+
+        // Seeded random and regular graphs for tests and benchmarks. Undirected models
+        // add every edge in both directions; DAGs and trees point away from their sources.
+        pub mod generators {
+            use super::{Graph, NodeLabel};
+            use crate::random::Rng;
+            use std::collections::BTreeSet;
+
+            fn undirected(
+                num_nodes: usize,
+                pairs: &BTreeSet<(NodeLabel, NodeLabel)>,
+            ) -> Graph<i32> {
+                let edges = pairs
+                    .iter()
+                    .flat_map(|(a, b)| vec![(*a, *b), (*b, *a)])
+                    .collect();
+                Graph::new_unweighted(vec![0; num_nodes], edges)
+            }
+
+            fn ordered(a: NodeLabel, b: NodeLabel) -> (NodeLabel, NodeLabel) {
+                if a < b {
+                    (a, b)
+                } else {
+                    (b, a)
+                }
+            }
+
+            // Erdos-Renyi G(n, p): every pair is an edge with probability p
+            pub fn gnp(num_nodes: usize, probability: f64, seed: u64) -> Graph<i32> {
+                let mut rng = Rng::new(seed);
+                let mut pairs = BTreeSet::new();
+                for a in 0..num_nodes {
+                    for b in a + 1..num_nodes {
+                        if rng.next_f64() < probability {
+                            pairs.insert((a, b));
+                        }
+                    }
+                }
+                undirected(num_nodes, &pairs)
+            }
+
+            // Erdos-Renyi G(n, m): m distinct edges chosen uniformly, capped at a complete graph
+            pub fn gnm(num_nodes: usize, num_edges: usize, seed: u64) -> Graph<i32> {
+                let mut rng = Rng::new(seed);
+                let possible = num_nodes * num_nodes.saturating_sub(1) / 2;
+                let num_edges = num_edges.min(possible);
+                let mut pairs = BTreeSet::new();
+
+                if num_edges * 2 > possible {
+                    // Dense: shuffle every pair and keep a prefix
+                    let mut all: Vec<(NodeLabel, NodeLabel)> = (0..num_nodes)
+                        .flat_map(|a| (a + 1..num_nodes).map(move |b| (a, b)))
+                        .collect();
+                    rng.shuffle(&mut all);
+                    pairs.extend(all.into_iter().take(num_edges));
+                } else {
+                    while pairs.len() < num_edges {
+                        let (a, b) = (rng.below(num_nodes), rng.below(num_nodes));
+                        if a != b {
+                            pairs.insert(ordered(a, b));
+                        }
+                    }
+                }
+                undirected(num_nodes, &pairs)
+            }
+
+            // Barabasi-Albert: each new node links to m existing nodes, picked in proportion
+            // to their degree. The first m + 1 nodes form a complete graph.
+            pub fn barabasi_albert(num_nodes: usize, m: usize, seed: u64) -> Graph<i32> {
+                let mut rng = Rng::new(seed);
+                let mut pairs = BTreeSet::new();
+                let initial = (m + 1).min(num_nodes);
+
+                // Every node appears here once per edge it touches
+                let mut endpoints = Vec::new();
+                for a in 0..initial {
+                    for b in a + 1..initial {
+                        pairs.insert((a, b));
+                        endpoints.push(a);
+                        endpoints.push(b);
+                    }
+                }
+
+                for node in initial..num_nodes {
+                    let mut targets = BTreeSet::new();
+                    while targets.len() < m.min(node) {
+                        let target = if endpoints.is_empty() {
+                            rng.below(node)
+                        } else {
+                            endpoints[rng.below(endpoints.len())]
+                        };
+                        targets.insert(target);
+                    }
+                    for target in targets {
+                        pairs.insert((target, node));
+                        endpoints.push(target);
+                        endpoints.push(node);
+                    }
+                }
+                undirected(num_nodes, &pairs)
+            }
+
+            // Random picks a Watts-Strogatz rewire tries before scanning for free nodes
+            const REWIRE_ATTEMPTS: usize = 16;
+
+            // Watts-Strogatz: a ring where each node meets its k nearest neighbours, then
+            // each edge's far end is moved to a random node with probability beta
+            pub fn watts_strogatz(num_nodes: usize, k: usize, beta: f64, seed: u64) -> Graph<i32> {
+                let mut rng = Rng::new(seed);
+                let mut pairs = BTreeSet::new();
+                let half = (k / 2).min(num_nodes.saturating_sub(1) / 2);
+
+                for node in 0..num_nodes {
+                    for step in 1..=half {
+                        pairs.insert(ordered(node, (node + step) % num_nodes));
+                    }
+                }
+
+                for node in 0..num_nodes {
+                    for step in 1..=half {
+                        let old = ordered(node, (node + step) % num_nodes);
+                        if !pairs.contains(&old) || rng.next_f64() >= beta {
+                            continue;
+                        }
+                        // Sparse rings almost always hit a free node within a few tries.
+                        // Only dense graphs fall back to listing every free node.
+                        let is_free =
+                            |t: NodeLabel| t != node && !pairs.contains(&ordered(node, t));
+                        let sampled = (0..REWIRE_ATTEMPTS)
+                            .map(|_| rng.below(num_nodes))
+                            .find(|t| is_free(*t));
+                        let target = match sampled {
+                            Some(target) => target,
+                            None => {
+                                let free: Vec<NodeLabel> =
+                                    (0..num_nodes).filter(|t| is_free(*t)).collect();
+                                if free.is_empty() {
+                                    continue;
+                                }
+                                free[rng.below(free.len())]
+                            }
+                        };
+                        pairs.remove(&old);
+                        pairs.insert(ordered(node, target));
+                    }
+                }
+                undirected(num_nodes, &pairs)
+            }
+
+            // Random topological order, then each forward pair is an edge with probability p
+            pub fn random_dag(num_nodes: usize, probability: f64, seed: u64) -> Graph<i32> {
+                let mut rng = Rng::new(seed);
+                let mut order: Vec<NodeLabel> = (0..num_nodes).collect();
+                rng.shuffle(&mut order);
+
+                let mut edges = Vec::new();
+                for i in 0..num_nodes {
+                    for j in i + 1..num_nodes {
+                        if rng.next_f64() < probability {
+                            edges.push((order[i], order[j]));
+                        }
+                    }
+                }
+                Graph::new_unweighted(vec![0; num_nodes], edges)
+            }
+
+            // Uniformly random labelled tree from a Prufer sequence, with edges pointing
+            // away from node 0
+            pub fn random_tree(num_nodes: usize, seed: u64) -> Graph<i32> {
+                let mut rng = Rng::new(seed);
+                let mut pairs = Vec::new();
+
+                if num_nodes == 2 {
+                    pairs.push((0, 1));
+                } else if num_nodes > 2 {
+                    let sequence: Vec<NodeLabel> =
+                        (0..num_nodes - 2).map(|_| rng.below(num_nodes)).collect();
+                    let mut degrees = vec![1; num_nodes];
+                    for node in sequence.iter() {
+                        degrees[*node] += 1;
+                    }
+
+                    let mut leaves: BTreeSet<NodeLabel> =
+                        (0..num_nodes).filter(|n| degrees[*n] == 1).collect();
+                    for node in sequence.iter() {
+                        let leaf = *leaves.iter().next().unwrap_or(&0);
+                        leaves.remove(&leaf);
+                        pairs.push((leaf, *node));
+                        degrees[*node] -= 1;
+                        if degrees[*node] == 1 {
+                            leaves.insert(*node);
+                        }
+                    }
+                    let last: Vec<NodeLabel> = leaves.into_iter().collect();
+                    pairs.push((last[0], last[1]));
+                }
+
+                // Orient away from node 0
+                let mut neighbours = vec![Vec::new(); num_nodes];
+                for (a, b) in pairs {
+                    neighbours[a].push(b);
+                    neighbours[b].push(a);
+                }
+                let mut edges = Vec::new();
+                let mut seen = vec![false; num_nodes];
+                let mut stack = Vec::new();
+                if num_nodes > 0 {
+                    seen[0] = true;
+                    stack.push(0);
+                }
+                while let Some(node) = stack.pop() {
+                    for neighbour in neighbours[node].iter() {
+                        if !seen[*neighbour] {
+                            seen[*neighbour] = true;
+                            edges.push((node, *neighbour));
+                            stack.push(*neighbour);
+                        }
+                    }
+                }
+                Graph::new_unweighted(vec![0; num_nodes], edges)
+            }
+
+            // width x height grid with 4-neighbour moves. Node y * width + x is cell (x, y).
+            // Each cell is blocked with the given probability; blocked cells have payload 1
+            // and no edges.
+            pub fn grid(
+                width: usize,
+                height: usize,
+                obstacle_probability: f64,
+                seed: u64,
+            ) -> Graph<i32> {
+                let mut rng = Rng::new(seed);
+                let num_nodes = width * height;
+                let blocked: Vec<bool> = (0..num_nodes)
+                    .map(|_| rng.next_f64() < obstacle_probability)
+                    .collect();
+
+                let mut edges = Vec::new();
+                for y in 0..height {
+                    for x in 0..width {
+                        let node = y * width + x;
+                        let right = node + 1;
+                        let down = node + width;
+                        if x + 1 < width && !blocked[node] && !blocked[right] {
+                            edges.push((node, right));
+                            edges.push((right, node));
+                        }
+                        if y + 1 < height && !blocked[node] && !blocked[down] {
+                            edges.push((node, down));
+                            edges.push((down, node));
+                        }
+                    }
+                }
+                let nodes = blocked.into_iter().map(|b| if b { 1 } else { 0 }).collect();
+                Graph::new_unweighted(nodes, edges)
+            }
+
+            pub fn complete(num_nodes: usize) -> Graph<i32> {
+                let pairs = (0..num_nodes)
+                    .flat_map(|a| (a + 1..num_nodes).map(move |b| (a, b)))
+                    .collect();
+                undirected(num_nodes, &pairs)
+            }
+
+            // Nodes 0..left on one side, left..left + right on the other
+            pub fn complete_bipartite(left: usize, right: usize) -> Graph<i32> {
+                let pairs = (0..left)
+                    .flat_map(|a| (left..left + right).map(move |b| (a, b)))
+                    .collect();
+                undirected(left + right, &pairs)
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn test_erdos_renyi() {
+                    let graph = gnm(20, 30, 1);
+                    assert_eq!(graph.num_nodes(), 20);
+                    assert_eq!(graph.num_edges(), 60);
+                    assert_eq!(gnm(5, 100, 1).num_edges(), 20);
+
+                    assert_eq!(gnp(10, 0.0, 1).num_edges(), 0);
+                    assert_eq!(gnp(10, 1.0, 1).num_edges(), 90);
+                    assert_eq!(gnp(30, 0.3, 9).edge_list(), gnp(30, 0.3, 9).edge_list());
+                    assert_ne!(gnp(30, 0.3, 9).edge_list(), gnp(30, 0.3, 10).edge_list());
+                }
+
+                #[test]
+                fn test_barabasi_albert() {
+                    let graph = barabasi_albert(50, 2, 3);
+                    // K3 to start, then two edges for each of the other 47 nodes
+                    assert_eq!(graph.num_edges(), 2 * (3 + 2 * 47));
+                    assert_eq!(graph.component_sizes(), vec![50]);
+                }
+
+                #[test]
+                fn test_watts_strogatz() {
+                    let ring = watts_strogatz(10, 4, 0.0, 1);
+                    assert_eq!(ring.num_edges(), 2 * 20);
+                    assert_eq!(ring.triangle_count(), 10);
+
+                    let rewired = watts_strogatz(10, 4, 0.5, 1);
+                    assert_eq!(rewired.num_edges(), 2 * 20);
+                    assert_ne!(rewired.edge_list(), ring.edge_list());
+                    assert_eq!(rewired.self_loops(), 0);
+
+                    // Nearly complete, so most rewires have to scan for the few free nodes
+                    let dense = watts_strogatz(7, 4, 1.0, 3);
+                    assert_eq!(dense.num_edges(), 2 * 14);
+                    assert_eq!(dense.self_loops(), 0);
+                }
+
+                #[test]
+                fn test_random_dag_and_tree() {
+                    let dag = random_dag(20, 0.3, 4);
+                    assert!(dag
+                        .strongly_connected_components()
+                        .iter()
+                        .all(|c| c.len() == 1));
+
+                    for n in 0..10 {
+                        let tree = random_tree(n, n as u64);
+                        assert_eq!(tree.num_nodes(), n);
+                        if n > 0 {
+                            assert!(tree.is_arborescence(0));
+                        }
+                    }
+                }
+
+                #[test]
+                fn test_grid() {
+                    let graph = grid(3, 2, 0.0, 1);
+                    assert_eq!(graph.num_edges(), 2 * 7);
+                    assert_eq!(graph.bfs(0, 5), Some(vec![0, 1, 2, 5]));
+
+                    let graph = grid(3, 3, 1.0, 1);
+                    assert_eq!(graph.num_edges(), 0);
+                }
+
+                #[test]
+                fn test_complete() {
+                    assert_eq!(complete(5).num_edges(), 20);
+                    assert_eq!(complete(5).maximum_clique().len(), 5);
+                    let graph = complete_bipartite(2, 3);
+                    assert_eq!(graph.num_edges(), 12);
+                    assert_eq!(graph.chromatic_number(), 2);
+                }
+            }
+        }
+
+        // VF2 (Cordella et al.) graph and subgraph isomorphism. A match maps every pattern
+        // node, by label, to a target node.
+        pub mod isomorphism {
+            use super::{EdgeWeight, Graph, NodeLabel};
+
+            #[derive(Debug, Clone, Copy, PartialEq)]
+            pub enum MatchKind {
+                // Same edges both ways round
+                Isomorphism,
+                // The pattern appears with exactly the edges the matched target nodes have
+                InducedSubgraph,
+                // The pattern's edges appear, but the target may have extra ones
+                Monomorphism,
+            }
+
+            pub type NodeMatch<T, U> = fn(&T, &U) -> bool;
+            pub type EdgeMatch = fn(EdgeWeight, EdgeWeight) -> bool;
+
+            fn any_node<T, U>(_pattern: &T, _target: &U) -> bool {
+                true
+            }
+
+            fn any_edge(_pattern: EdgeWeight, _target: EdgeWeight) -> bool {
+                true
+            }
+
+            // One level of the search: the pattern node being placed and where it could go
+            struct Frame {
+                pattern_node: NodeLabel,
+                candidates: Vec<NodeLabel>,
+                next: usize,
+                matched: Option<NodeLabel>,
+            }
+
+            // Lazily yields every match, depth first
+            pub struct Matches<'a, T, U, NM, EM> {
+                pattern: &'a Graph<T>,
+                target: &'a Graph<U>,
+                kind: MatchKind,
+                node_match: NM,
+                edge_match: EM,
+                pattern_predecessors: Vec<Vec<NodeLabel>>,
+                target_predecessors: Vec<Vec<NodeLabel>>,
+                core_pattern: Vec<Option<NodeLabel>>,
+                core_target: Vec<Option<NodeLabel>>,
+                // Depth at which a node joined the in/out terminal sets, 0 if it hasn't
+                in_pattern: Vec<usize>,
+                out_pattern: Vec<usize>,
+                in_target: Vec<usize>,
+                out_target: Vec<usize>,
+                depth: usize,
+                stack: Vec<Frame>,
+                started: bool,
+            }
+
+            impl<T> Graph<T> {
+                // Match self as the pattern inside target. node_match and edge_match decide
+                // whether a pattern node or edge weight may be paired with a target one.
+                pub fn matches<'a, U, NM, EM>(
+                    &'a self,
+                    target: &'a Graph<U>,
+                    kind: MatchKind,
+                    node_match: NM,
+                    edge_match: EM,
+                ) -> Matches<'a, T, U, NM, EM>
+                where
+                    NM: Fn(&T, &U) -> bool,
+                    EM: Fn(EdgeWeight, EdgeWeight) -> bool,
+                {
+                    Matches {
+                        pattern: self,
+                        target,
+                        kind,
+                        node_match,
+                        edge_match,
+                        pattern_predecessors: predecessors(self),
+                        target_predecessors: predecessors(target),
+                        core_pattern: vec![None; self.nodes.len()],
+                        core_target: vec![None; target.nodes.len()],
+                        in_pattern: vec![0; self.nodes.len()],
+                        out_pattern: vec![0; self.nodes.len()],
+                        in_target: vec![0; target.nodes.len()],
+                        out_target: vec![0; target.nodes.len()],
+                        depth: 0,
+                        stack: Vec::new(),
+                        started: false,
+                    }
+                }
+
+                // Ignores node payloads and edge weights
+                pub fn isomorphism<U>(&self, other: &Graph<U>) -> Option<Vec<NodeLabel>> {
+                    self.matches(other, MatchKind::Isomorphism, any_node, any_edge)
+                        .next()
+                }
+
+                pub fn is_isomorphic<U>(&self, other: &Graph<U>) -> bool {
+                    self.isomorphism(other).is_some()
+                }
+
+                // Every way self appears inside target, ignoring payloads and weights
+                pub fn subgraph_matches<'a, U>(
+                    &'a self,
+                    target: &'a Graph<U>,
+                    kind: MatchKind,
+                ) -> Matches<'a, T, U, NodeMatch<T, U>, EdgeMatch> {
+                    self.matches(target, kind, any_node, any_edge)
+                }
+            }
+
+            fn predecessors<T>(graph: &Graph<T>) -> Vec<Vec<NodeLabel>> {
+                let mut predecessors = vec![Vec::new(); graph.nodes.len()];
+                for (node, edges) in graph.edges.iter().enumerate() {
+                    for edge_node in edges.keys() {
+                        predecessors[*edge_node].push(node);
+                    }
+                }
+                predecessors
+            }
+
+            // Neighbours of a new pair that are in the in set, the out set, or neither
+            #[derive(Default)]
+            struct Counts {
+                incoming: usize,
+                outgoing: usize,
+                new: usize,
+            }
+
+            impl Counts {
+                fn add(&mut self, incoming: usize, outgoing: usize) {
+                    if incoming > 0 {
+                        self.incoming += 1;
+                    }
+                    if outgoing > 0 {
+                        self.outgoing += 1;
+                    }
+                    if incoming == 0 && outgoing == 0 {
+                        self.new += 1;
+                    }
+                }
+
+                fn fits(&self, target: &Counts, kind: MatchKind) -> bool {
+                    match kind {
+                        MatchKind::Isomorphism => {
+                            self.incoming == target.incoming
+                                && self.outgoing == target.outgoing
+                                && self.new == target.new
+                        }
+                        MatchKind::InducedSubgraph => {
+                            self.incoming <= target.incoming
+                                && self.outgoing <= target.outgoing
+                                && self.new <= target.new
+                        }
+                        MatchKind::Monomorphism => {
+                            self.incoming <= target.incoming && self.outgoing <= target.outgoing
+                        }
+                    }
+                }
+            }
+
+            impl<'a, T, U, NM, EM> Matches<'a, T, U, NM, EM>
+            where
+                NM: Fn(&T, &U) -> bool,
+                EM: Fn(EdgeWeight, EdgeWeight) -> bool,
+            {
+                // VF2's candidate pairs: extend from the out terminal sets if both have
+                // nodes, then the in sets, then anything unmatched
+                fn frame(&self) -> Frame {
+                    let unmatched_pattern = |set: &[usize]| {
+                        (0..self.pattern.nodes.len()).find(|n| {
+                            self.core_pattern[*n].is_none() && (set.is_empty() || set[*n] > 0)
+                        })
+                    };
+                    let unmatched_target = |set: &[usize]| -> Vec<NodeLabel> {
+                        (0..self.target.nodes.len())
+                            .filter(|n| {
+                                self.core_target[*n].is_none() && (set.is_empty() || set[*n] > 0)
+                            })
+                            .collect()
+                    };
+
+                    let terminal_sets = [
+                        (&self.out_pattern, &self.out_target),
+                        (&self.in_pattern, &self.in_target),
+                    ];
+                    for (pattern_set, target_set) in terminal_sets.iter() {
+                        let candidates = unmatched_target(target_set);
+                        if let (Some(pattern_node), false) =
+                            (unmatched_pattern(pattern_set), candidates.is_empty())
+                        {
+                            return Frame {
+                                pattern_node,
+                                candidates,
+                                next: 0,
+                                matched: None,
+                            };
+                        }
+                    }
+
+                    Frame {
+                        pattern_node: unmatched_pattern(&[]).unwrap_or(0),
+                        candidates: unmatched_target(&[]),
+                        next: 0,
+                        matched: None,
+                    }
+                }
+
+                fn feasible(&self, pattern_node: NodeLabel, target_node: NodeLabel) -> bool {
+                    if !(self.node_match)(
+                        &self.pattern.nodes[pattern_node],
+                        &self.target.nodes[target_node],
+                    ) {
+                        return false;
+                    }
+                    let induced = self.kind != MatchKind::Monomorphism;
+                    let pattern_edges = &self.pattern.edges;
+                    let target_edges = &self.target.edges;
+
+                    match (
+                        pattern_edges[pattern_node].get(&pattern_node),
+                        target_edges[target_node].get(&target_node),
+                    ) {
+                        (Some(a), Some(b)) if !(self.edge_match)(*a, *b) => return false,
+                        (Some(_), None) => return false,
+                        (None, Some(_)) if induced => return false,
+                        _ => {}
+                    }
+
+                    let mut pattern_counts = Counts::default();
+                    for (successor, weight) in pattern_edges[pattern_node].iter() {
+                        if *successor == pattern_node {
+                            continue;
+                        }
+                        match self.core_pattern[*successor] {
+                            Some(image) => match target_edges[target_node].get(&image) {
+                                Some(target_weight)
+                                    if (self.edge_match)(*weight, *target_weight) => {}
+                                _ => return false,
+                            },
+                            None => pattern_counts
+                                .add(self.in_pattern[*successor], self.out_pattern[*successor]),
+                        }
+                    }
+                    for predecessor in self.pattern_predecessors[pattern_node].iter() {
+                        if *predecessor == pattern_node {
+                            continue;
+                        }
+                        match self.core_pattern[*predecessor] {
+                            Some(image) => {
+                                let weight = pattern_edges[*predecessor][&pattern_node];
+                                match target_edges[image].get(&target_node) {
+                                    Some(target_weight)
+                                        if (self.edge_match)(weight, *target_weight) => {}
+                                    _ => return false,
+                                }
+                            }
+                            None => pattern_counts.add(
+                                self.in_pattern[*predecessor],
+                                self.out_pattern[*predecessor],
+                            ),
+                        }
+                    }
+
+                    let mut target_counts = Counts::default();
+                    for successor in target_edges[target_node].keys() {
+                        if *successor == target_node {
+                            continue;
+                        }
+                        match self.core_target[*successor] {
+                            Some(image)
+                                if induced && !pattern_edges[pattern_node].contains_key(&image) =>
+                            {
+                                return false
+                            }
+                            Some(_) => {}
+                            None => target_counts
+                                .add(self.in_target[*successor], self.out_target[*successor]),
+                        }
+                    }
+                    for predecessor in self.target_predecessors[target_node].iter() {
+                        if *predecessor == target_node {
+                            continue;
+                        }
+                        match self.core_target[*predecessor] {
+                            Some(image)
+                                if induced && !pattern_edges[image].contains_key(&pattern_node) =>
+                            {
+                                return false
+                            }
+                            Some(_) => {}
+                            None => target_counts
+                                .add(self.in_target[*predecessor], self.out_target[*predecessor]),
+                        }
+                    }
+
+                    pattern_counts.fits(&target_counts, self.kind)
+                }
+
+                fn add_pair(&mut self, pattern_node: NodeLabel, target_node: NodeLabel) {
+                    self.depth += 1;
+                    let depth = self.depth;
+                    self.core_pattern[pattern_node] = Some(target_node);
+                    self.core_target[target_node] = Some(pattern_node);
+
+                    let stamp = |set: &mut Vec<usize>, node: NodeLabel| {
+                        if set[node] == 0 {
+                            set[node] = depth;
+                        }
+                    };
+                    stamp(&mut self.in_pattern, pattern_node);
+                    stamp(&mut self.out_pattern, pattern_node);
+                    stamp(&mut self.in_target, target_node);
+                    stamp(&mut self.out_target, target_node);
+                    for successor in self.pattern.edges[pattern_node].keys() {
+                        stamp(&mut self.out_pattern, *successor);
+                    }
+                    for predecessor in self.pattern_predecessors[pattern_node].iter() {
+                        stamp(&mut self.in_pattern, *predecessor);
+                    }
+                    for successor in self.target.edges[target_node].keys() {
+                        stamp(&mut self.out_target, *successor);
+                    }
+                    for predecessor in self.target_predecessors[target_node].iter() {
+                        stamp(&mut self.in_target, *predecessor);
+                    }
+                }
+
+                fn remove_pair(&mut self, pattern_node: NodeLabel, target_node: NodeLabel) {
+                    let depth = self.depth;
+                    let unstamp = |set: &mut Vec<usize>, node: NodeLabel| {
+                        if set[node] == depth {
+                            set[node] = 0;
+                        }
+                    };
+                    unstamp(&mut self.in_pattern, pattern_node);
+                    unstamp(&mut self.out_pattern, pattern_node);
+                    unstamp(&mut self.in_target, target_node);
+                    unstamp(&mut self.out_target, target_node);
+                    for successor in self.pattern.edges[pattern_node].keys() {
+                        unstamp(&mut self.out_pattern, *successor);
+                    }
+                    for predecessor in self.pattern_predecessors[pattern_node].iter() {
+                        unstamp(&mut self.in_pattern, *predecessor);
+                    }
+                    for successor in self.target.edges[target_node].keys() {
+                        unstamp(&mut self.out_target, *successor);
+                    }
+                    for predecessor in self.target_predecessors[target_node].iter() {
+                        unstamp(&mut self.in_target, *predecessor);
+                    }
+
+                    self.core_pattern[pattern_node] = None;
+                    self.core_target[target_node] = None;
+                    self.depth -= 1;
+                }
+            }
+
+            impl<'a, T, U, NM, EM> Iterator for Matches<'a, T, U, NM, EM>
+            where
+                NM: Fn(&T, &U) -> bool,
+                EM: Fn(EdgeWeight, EdgeWeight) -> bool,
+            {
+                type Item = Vec<NodeLabel>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    let pattern_size = self.pattern.nodes.len();
+                    if !self.started {
+                        self.started = true;
+                        let target_size = self.target.nodes.len();
+                        let sizes_fit = match self.kind {
+                            MatchKind::Isomorphism => {
+                                pattern_size == target_size
+                                    && self.pattern.num_edges() == self.target.num_edges()
+                            }
+                            _ => pattern_size <= target_size,
+                        };
+                        if !sizes_fit {
+                            return None;
+                        }
+                        if pattern_size == 0 {
+                            return Some(Vec::new());
+                        }
+                        let frame = self.frame();
+                        self.stack.push(frame);
+                    }
+
+                    loop {
+                        let (pattern_node, previous) = match self.stack.last_mut() {
+                            Some(frame) => (frame.pattern_node, frame.matched.take()),
+                            None => return None,
+                        };
+                        if let Some(target_node) = previous {
+                            self.remove_pair(pattern_node, target_node);
+                        }
+
+                        let candidate = match self.stack.last_mut() {
+                            Some(frame) if frame.next < frame.candidates.len() => {
+                                frame.next += 1;
+                                frame.candidates[frame.next - 1]
+                            }
+                            _ => {
+                                self.stack.pop();
+                                continue;
+                            }
+                        };
+                        if !self.feasible(pattern_node, candidate) {
+                            continue;
+                        }
+
+                        self.add_pair(pattern_node, candidate);
+                        if let Some(frame) = self.stack.last_mut() {
+                            frame.matched = Some(candidate);
+                        }
+                        if self.depth == pattern_size {
+                            return Some(
+                                self.core_pattern.iter().map(|n| n.unwrap_or(0)).collect(),
+                            );
+                        }
+                        let frame = self.frame();
+                        self.stack.push(frame);
+                    }
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                fn undirected(num_nodes: usize, pairs: &[(NodeLabel, NodeLabel)]) -> Graph<i32> {
+                    let edges = pairs
+                        .iter()
+                        .flat_map(|(a, b)| vec![(*a, *b), (*b, *a)])
+                        .collect();
+                    Graph::new_unweighted(vec![0; num_nodes], edges)
+                }
+
+                fn complete(num_nodes: usize) -> Graph<i32> {
+                    let pairs: Vec<(NodeLabel, NodeLabel)> = (0..num_nodes)
+                        .flat_map(|a| (a + 1..num_nodes).map(move |b| (a, b)))
+                        .collect();
+                    undirected(num_nodes, &pairs)
+                }
+
+                #[test]
+                fn test_isomorphism() {
+                    let cycle =
+                        Graph::new_unweighted(vec![0; 4], vec![(0, 1), (1, 2), (2, 3), (3, 0)]);
+                    let relabelled =
+                        Graph::new_unweighted(vec![0; 4], vec![(2, 0), (0, 3), (3, 1), (1, 2)]);
+                    let mapping = cycle.isomorphism(&relabelled).unwrap();
+                    for (a, b, _weight) in cycle.edge_list() {
+                        assert!(relabelled
+                            .edge_list()
+                            .iter()
+                            .any(|e| (e.0, e.1) == (mapping[a], mapping[b])));
+                    }
+
+                    // Reversing one edge breaks it
+                    let bent =
+                        Graph::new_unweighted(vec![0; 4], vec![(0, 1), (1, 2), (2, 3), (0, 3)]);
+                    assert!(!cycle.is_isomorphic(&bent));
+
+                    // Same degree sequence, different shape
+                    let path = undirected(4, &[(0, 1), (1, 2), (2, 3)]);
+                    let star = undirected(4, &[(0, 1), (0, 2), (0, 3)]);
+                    assert!(!path.is_isomorphic(&star));
+                    assert!(path.is_isomorphic(&undirected(4, &[(3, 0), (0, 2), (2, 1)])));
+                }
+
+                #[test]
+                fn test_predicates() {
+                    let pattern = Graph::new(vec!['a', 'b'], vec![(0, 1, 5)]);
+                    let target = Graph::new(vec!['b', 'a', 'a'], vec![(1, 0, 5), (2, 0, 7)]);
+
+                    let matches: Vec<Vec<NodeLabel>> = pattern
+                        .matches(
+                            &target,
+                            MatchKind::Monomorphism,
+                            |a, b| a == b,
+                            |a, b| a == b,
+                        )
+                        .collect();
+                    assert_eq!(matches, vec![vec![1, 0]]);
+
+                    let matches = pattern
+                        .matches(&target, MatchKind::Monomorphism, |a, b| a == b, |_, _| true)
+                        .count();
+                    assert_eq!(matches, 2);
+                }
+
+                #[test]
+                fn test_subgraph_matches() {
+                    let triangle = complete(3);
+                    // 4 triangles in K4, each in 6 orientations
+                    assert_eq!(
+                        triangle
+                            .subgraph_matches(&complete(4), MatchKind::Monomorphism)
+                            .count(),
+                        24
+                    );
+                    assert_eq!(
+                        triangle
+                            .subgraph_matches(&complete(4), MatchKind::InducedSubgraph)
+                            .count(),
+                        24
+                    );
+
+                    let path = undirected(3, &[(0, 1), (1, 2)]);
+                    let cycle = undirected(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+                    assert_eq!(
+                        path.subgraph_matches(&cycle, MatchKind::InducedSubgraph)
+                            .count(),
+                        10
+                    );
+                    assert_eq!(
+                        path.subgraph_matches(&complete(4), MatchKind::InducedSubgraph)
+                            .count(),
+                        0
+                    );
+                    assert_eq!(
+                        path.subgraph_matches(&complete(4), MatchKind::Monomorphism)
+                            .count(),
+                        24
+                    );
+                    assert_eq!(
+                        complete(4)
+                            .subgraph_matches(&path, MatchKind::Monomorphism)
+                            .count(),
+                        0
+                    );
+                }
+            }
+        }
+
+        // Travelling salesman tours over a distance matrix, where None means there is no
+        // edge. A tour lists every node once, starting from its first node, and returns to it.
+        pub mod tsp {
+            use super::{Graph, NodeLabel};
+            use std::error::Error;
+            use std::fmt;
+
+            pub type DistanceMatrix = Vec<Vec<Option<i64>>>;
+
+            // Held-Karp needs 2^n * n table entries
+            pub const MAX_HELD_KARP_NODES: usize = 20;
+
+            // Above this many odd-degree nodes Christofides matches them greedily, which loses
+            // its 3/2 guarantee
+            const MAX_EXACT_MATCHING: usize = 20;
+
+            #[derive(Debug, PartialEq)]
+            pub struct Tour {
+                pub order: Vec<NodeLabel>,
+                pub cost: i64,
+            }
+
+            #[derive(Debug, PartialEq)]
+            pub enum TspError {
+                Empty,
+                NotSquare,
+                StartOutOfRange(NodeLabel),
+                TooLarge(usize),
+                MissingEdge(NodeLabel, NodeLabel),
+                Asymmetric(NodeLabel, NodeLabel),
+                NoTour,
+            }
+
+            impl fmt::Display for TspError {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        TspError::Empty => write!(f, "there are no nodes to visit"),
+                        TspError::NotSquare => write!(f, "the distance matrix is not square"),
+                        TspError::StartOutOfRange(start) => {
+                            write!(f, "start {} is not a node in the graph", start)
+                        }
+                        TspError::TooLarge(num_nodes) => write!(
+                            f,
+                            "{} nodes is more than the {} Held-Karp supports",
+                            num_nodes, MAX_HELD_KARP_NODES
+                        ),
+                        TspError::MissingEdge(u, v) => {
+                            write!(f, "there is no edge from {} to {}", u, v)
+                        }
+                        TspError::Asymmetric(u, v) => {
+                            write!(
+                                f,
+                                "the distance from {} to {} differs from the way back",
+                                u, v
+                            )
+                        }
+                        TspError::NoTour => write!(f, "no tour visits every node"),
+                    }
+                }
+            }
+
+            impl Error for TspError {}
+
+            impl<T> Graph<T> {
+                // Edge weights, with 0 from every node to itself
+                pub fn distance_matrix(&self) -> DistanceMatrix {
+                    let num_nodes = self.nodes.len();
+                    let mut matrix = vec![vec![None; num_nodes]; num_nodes];
+                    for (node, edges) in self.edges.iter().enumerate() {
+                        for (edge_node, weight) in edges.iter() {
+                            matrix[node][*edge_node] = Some(i64::from(*weight));
+                        }
+                        matrix[node][node] = Some(0);
+                    }
+                    matrix
+                }
+
+                pub fn held_karp_tour(&self) -> Result<Tour, TspError> {
+                    held_karp(&self.distance_matrix())
+                }
+
+                pub fn nearest_neighbour_tour(&self, start: NodeLabel) -> Result<Tour, TspError> {
+                    nearest_neighbour(&self.distance_matrix(), start)
+                }
+
+                pub fn christofides_tour(&self) -> Result<Tour, TspError> {
+                    christofides(&self.distance_matrix())
+                }
+            }
+
+            fn check_square(matrix: &[Vec<Option<i64>>]) -> Result<usize, TspError> {
+                if matrix.is_empty() {
+                    return Err(TspError::Empty);
+                }
+                if matrix.iter().any(|row| row.len() != matrix.len()) {
+                    return Err(TspError::NotSquare);
+                }
+                Ok(matrix.len())
+            }
+
+            // Cost of visiting order and returning to the start, None if an edge is missing
+            pub fn tour_cost(matrix: &[Vec<Option<i64>>], order: &[NodeLabel]) -> Option<i64> {
+                let mut cost = 0;
+                for (i, node) in order.iter().enumerate() {
+                    cost += matrix[*node][order[(i + 1) % order.len()]]?;
+                }
+                Some(cost)
+            }
+
+            // Exact dynamic programming over subsets of the nodes, starting from node 0
+            pub fn held_karp(matrix: &[Vec<Option<i64>>]) -> Result<Tour, TspError> {
+                let num_nodes = check_square(matrix)?;
+                if num_nodes > MAX_HELD_KARP_NODES {
+                    return Err(TspError::TooLarge(num_nodes));
+                }
+                if num_nodes == 1 {
+                    return Ok(Tour {
+                        order: vec![0],
+                        cost: 0,
+                    });
+                }
+
+                // Node i + 1 is bit i; best[mask * rest + i] is the cheapest path from 0
+                // through mask that ends at node i + 1
+                let rest = num_nodes - 1;
+                let full = (1usize << rest) - 1;
+                let mut best: Vec<Option<i64>> = vec![None; (full + 1) * rest];
+                let mut previous = vec![0usize; (full + 1) * rest];
+                for last in 0..rest {
+                    best[(1 << last) * rest + last] = matrix[0][last + 1];
+                }
+
+                for mask in 1..=full {
+                    for last in 0..rest {
+                        let cost = match best[mask * rest + last] {
+                            Some(cost) if mask & (1 << last) != 0 => cost,
+                            _ => continue,
+                        };
+                        for next in (0..rest).filter(|next| mask & (1 << next) == 0) {
+                            let distance = match matrix[last + 1][next + 1] {
+                                Some(distance) => distance,
+                                None => continue,
+                            };
+                            let index = (mask | (1 << next)) * rest + next;
+                            if best[index].map_or(true, |known| cost + distance < known) {
+                                best[index] = Some(cost + distance);
+                                previous[index] = last;
+                            }
+                        }
+                    }
+                }
+
+                let mut cheapest: Option<(i64, usize)> = None;
+                for last in 0..rest {
+                    if let (Some(cost), Some(back)) =
+                        (best[full * rest + last], matrix[last + 1][0])
+                    {
+                        if cheapest.map_or(true, |(known, _)| cost + back < known) {
+                            cheapest = Some((cost + back, last));
+                        }
+                    }
+                }
+                let (cost, mut last) = cheapest.ok_or(TspError::NoTour)?;
+
+                let mut order = Vec::with_capacity(num_nodes);
+                let mut mask = full;
+                while mask != 0 {
+                    order.push(last + 1);
+                    let before = previous[mask * rest + last];
+                    mask &= !(1 << last);
+                    last = before;
+                }
+                order.push(0);
+                order.reverse();
+                Ok(Tour { order, cost })
+            }
+
+            // Always go to the closest unvisited node
+            pub fn nearest_neighbour(
+                matrix: &[Vec<Option<i64>>],
+                start: NodeLabel,
+            ) -> Result<Tour, TspError> {
+                let num_nodes = check_square(matrix)?;
+                if start >= num_nodes {
+                    return Err(TspError::StartOutOfRange(start));
+                }
+
+                let mut visited = vec![false; num_nodes];
+                visited[start] = true;
+                let mut order = vec![start];
+                let mut current = start;
+                while order.len() < num_nodes {
+                    let closest = (0..num_nodes)
+                        .filter(|node| !visited[*node])
+                        .filter_map(|node| matrix[current][node].map(|distance| (distance, node)))
+                        .min()
+                        .ok_or(TspError::NoTour)?;
+                    current = closest.1;
+                    visited[current] = true;
+                    order.push(current);
+                }
+
+                let cost = tour_cost(matrix, &order).ok_or(TspError::NoTour)?;
+                Ok(Tour { order, cost })
+            }
+
+            // Reverse segments while that makes the tour cheaper. The first node stays put.
+            // With a symmetric matrix only the two edges at the ends of the segment change, so
+            // each candidate costs O(1); otherwise the reversed segment's own edges change
+            // direction and the whole tour is re-costed.
+            pub fn two_opt(matrix: &[Vec<Option<i64>>], tour: &Tour) -> Tour {
+                let mut order = tour.order.clone();
+                let mut cost = tour.cost;
+                let num_nodes = order.len();
+                let symmetric =
+                    (0..num_nodes).all(|u| (0..u).all(|v| matrix[u][v] == matrix[v][u]));
+                let mut improved = true;
+                while improved {
+                    improved = false;
+                    for i in 1..num_nodes {
+                        for j in i + 1..num_nodes {
+                            let new_cost = if symmetric {
+                                let (a, b) = (order[i - 1], order[i]);
+                                let (c, d) = (order[j], order[(j + 1) % num_nodes]);
+                                match (matrix[a][c], matrix[b][d], matrix[a][b], matrix[c][d]) {
+                                    (Some(ac), Some(bd), Some(ab), Some(cd)) => {
+                                        Some(cost + ac + bd - ab - cd)
+                                    }
+                                    _ => None,
+                                }
+                            } else {
+                                order[i..=j].reverse();
+                                let new_cost = tour_cost(matrix, &order);
+                                order[i..=j].reverse();
+                                new_cost
+                            };
+                            if let Some(new_cost) = new_cost.filter(|new_cost| *new_cost < cost) {
+                                order[i..=j].reverse();
+                                cost = new_cost;
+                                improved = true;
+                            }
+                        }
+                    }
+                }
+                Tour { order, cost }
+            }
+
+            // Move runs of one to three nodes elsewhere in the tour while that makes it
+            // cheaper. The first node stays put.
+            pub fn or_opt(matrix: &[Vec<Option<i64>>], tour: &Tour) -> Tour {
+                let mut order = tour.order.clone();
+                let mut cost = tour.cost;
+                let mut improved = true;
+                while improved {
+                    improved = false;
+                    for length in 1..=3 {
+                        for start in 1..order.len().saturating_sub(length - 1) {
+                            let mut rest = order.clone();
+                            let segment: Vec<NodeLabel> =
+                                rest.drain(start..start + length).collect();
+                            for position in 1..=rest.len() {
+                                if position == start {
+                                    continue;
+                                }
+                                let mut candidate = rest.clone();
+                                candidate.splice(position..position, segment.iter().cloned());
+                                if let Some(new_cost) = tour_cost(matrix, &candidate) {
+                                    if new_cost < cost {
+                                        order = candidate;
+                                        cost = new_cost;
+                                        improved = true;
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Tour { order, cost }
+            }
+
+            // Minimum spanning tree, plus a minimum matching of its odd-degree nodes, gives an
+            // Eulerian multigraph; skipping repeats in its circuit gives a tour. Needs a
+            // complete symmetric matrix, and is within 3/2 of optimal when it is metric.
+            pub fn christofides(matrix: &[Vec<Option<i64>>]) -> Result<Tour, TspError> {
+                let num_nodes = check_square(matrix)?;
+                let mut distances = vec![vec![0; num_nodes]; num_nodes];
+                for u in 0..num_nodes {
+                    for v in 0..num_nodes {
+                        if u == v {
+                            continue;
+                        }
+                        distances[u][v] = match (matrix[u][v], matrix[v][u]) {
+                            (Some(a), Some(b)) if a == b => a,
+                            (Some(_), Some(_)) => return Err(TspError::Asymmetric(u, v)),
+                            (None, _) => return Err(TspError::MissingEdge(u, v)),
+                            (_, None) => return Err(TspError::MissingEdge(v, u)),
+                        };
+                    }
+                }
+
+                // Prim's algorithm
+                let mut multigraph: Vec<Vec<(NodeLabel, usize)>> = vec![Vec::new(); num_nodes];
+                let mut num_edges = 0;
+                let mut add_edge =
+                    |multigraph: &mut Vec<Vec<(NodeLabel, usize)>>, u: NodeLabel, v: NodeLabel| {
+                        multigraph[u].push((v, num_edges));
+                        multigraph[v].push((u, num_edges));
+                        num_edges += 1;
+                    };
+                let mut in_tree = vec![false; num_nodes];
+                let mut closest: Vec<(i64, NodeLabel)> =
+                    distances[0].iter().map(|d| (*d, 0)).collect();
+                in_tree[0] = true;
+                for _ in 1..num_nodes {
+                    let next = (0..num_nodes)
+                        .filter(|node| !in_tree[*node])
+                        .min_by_key(|node| closest[*node].0)
+                        .unwrap_or(0);
+                    in_tree[next] = true;
+                    add_edge(&mut multigraph, closest[next].1, next);
+                    for node in 0..num_nodes {
+                        if !in_tree[node] && distances[next][node] < closest[node].0 {
+                            closest[node] = (distances[next][node], next);
+                        }
+                    }
+                }
+
+                let odd: Vec<NodeLabel> = (0..num_nodes)
+                    .filter(|node| multigraph[*node].len() % 2 == 1)
+                    .collect();
+                for (u, v) in min_matching(&distances, &odd) {
+                    add_edge(&mut multigraph, u, v);
+                }
+
+                // Hierholzer's algorithm, keeping only the first visit to each node
+                let mut used = vec![false; num_edges];
+                let mut next_edge = vec![0; num_nodes];
+                let mut visited = vec![false; num_nodes];
+                let mut order = Vec::with_capacity(num_nodes);
+                let mut stack = vec![0];
+                while let Some(&node) = stack.last() {
+                    while next_edge[node] < multigraph[node].len()
+                        && used[multigraph[node][next_edge[node]].1]
+                    {
+                        next_edge[node] += 1;
+                    }
+                    match multigraph[node].get(next_edge[node]) {
+                        Some(&(edge_node, edge)) => {
+                            used[edge] = true;
+                            stack.push(edge_node);
+                        }
+                        None => {
+                            stack.pop();
+                            if !visited[node] {
+                                visited[node] = true;
+                                order.push(node);
+                            }
+                        }
+                    }
+                }
+                order.reverse();
+
+                let cost = tour_cost(matrix, &order).ok_or(TspError::NoTour)?;
+                Ok(Tour { order, cost })
+            }
+
+            // Pairs up nodes (an even number of them) as cheaply as possible
+            fn min_matching(
+                distances: &[Vec<i64>],
+                nodes: &[NodeLabel],
+            ) -> Vec<(NodeLabel, NodeLabel)> {
+                if nodes.len() > MAX_EXACT_MATCHING {
+                    let mut pairs: Vec<(i64, NodeLabel, NodeLabel)> = Vec::new();
+                    for (i, u) in nodes.iter().enumerate() {
+                        for v in nodes[i + 1..].iter() {
+                            pairs.push((distances[*u][*v], *u, *v));
+                        }
+                    }
+                    pairs.sort();
+                    let mut matched = vec![false; distances.len()];
+                    let mut matching = Vec::new();
+                    for (_distance, u, v) in pairs {
+                        if !matched[u] && !matched[v] {
+                            matched[u] = true;
+                            matched[v] = true;
+                            matching.push((u, v));
+                        }
+                    }
+                    return matching;
+                }
+
+                // best[mask] is the cheapest matching of the nodes in mask, always pairing its
+                // lowest node first
+                let full = (1usize << nodes.len()) - 1;
+                let mut best: Vec<Option<(i64, usize, usize)>> = vec![None; full + 1];
+                best[0] = Some((0, 0, 0));
+                for mask in 1..=full {
+                    if mask.count_ones() % 2 == 1 {
+                        continue;
+                    }
+                    let first = mask.trailing_zeros() as usize;
+                    for second in
+                        (first + 1..nodes.len()).filter(|second| mask & (1 << second) != 0)
+                    {
+                        let rest = mask & !(1 << first) & !(1 << second);
+                        if let Some((cost, _, _)) = best[rest] {
+                            let cost = cost + distances[nodes[first]][nodes[second]];
+                            if best[mask].map_or(true, |(known, _, _)| cost < known) {
+                                best[mask] = Some((cost, first, second));
+                            }
+                        }
+                    }
+                }
+
+                let mut matching = Vec::new();
+                let mut mask = full;
+                while mask != 0 {
+                    let (_cost, first, second) = best[mask].unwrap_or((0, 0, 0));
+                    matching.push((nodes[first], nodes[second]));
+                    mask &= !(1 << first) & !(1 << second);
+                }
+                matching
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                // Manhattan distances between points, which are metric
+                fn points(coordinates: &[(i64, i64)]) -> DistanceMatrix {
+                    coordinates
+                        .iter()
+                        .map(|(x1, y1)| {
+                            coordinates
+                                .iter()
+                                .map(|(x2, y2)| Some((x1 - x2).abs() + (y1 - y2).abs()))
+                                .collect()
+                        })
+                        .collect()
+                }
+
+                #[test]
+                fn test_rectangle() {
+                    let matrix = points(&[(0, 0), (3, 0), (3, 4), (0, 4)]);
+                    let optimal = held_karp(&matrix).unwrap();
+                    assert_eq!(optimal.cost, 14);
+                    assert!(optimal.order == vec![0, 1, 2, 3] || optimal.order == vec![0, 3, 2, 1]);
+                    assert_eq!(nearest_neighbour(&matrix, 0).unwrap().cost, 14);
+
+                    let crossed = Tour {
+                        order: vec![0, 2, 1, 3],
+                        cost: tour_cost(&matrix, &[0, 2, 1, 3]).unwrap(),
+                    };
+                    assert_eq!(crossed.cost, 22);
+                    assert_eq!(two_opt(&matrix, &crossed).cost, 14);
+                    assert_eq!(or_opt(&matrix, &crossed).cost, 14);
+                }
+
+                #[test]
+                fn test_two_opt_asymmetric() {
+                    // Going round 0 -> 1 -> 2 -> 3 is cheap, the other way is dear
+                    let matrix: Vec<Vec<Option<i64>>> = (0..4)
+                        .map(|u| {
+                            (0..4)
+                                .map(|v| match (v + 4 - u) % 4 {
+                                    0 => Some(0),
+                                    1 => Some(1),
+                                    _ => Some(10),
+                                })
+                                .collect()
+                        })
+                        .collect();
+                    let backwards = Tour {
+                        order: vec![0, 3, 2, 1],
+                        cost: 40,
+                    };
+                    // Reversing 3, 2 also turns the 2 -> 3 edge round, which the symmetric
+                    // shortcut would miss
+                    let improved = two_opt(&matrix, &backwards);
+                    assert_eq!(improved.order, vec![0, 2, 3, 1]);
+                    assert_eq!(improved.cost, 31);
+                    assert_eq!(tour_cost(&matrix, &improved.order), Some(31));
+                }
+
+                #[test]
+                fn test_heuristics_against_exact() {
+                    let coordinates: Vec<(i64, i64)> =
+                        (0..10).map(|i| ((i * 7) % 11, (i * i * 3) % 13)).collect();
+                    let matrix = points(&coordinates);
+                    let optimal = held_karp(&matrix).unwrap();
+                    assert_eq!(tour_cost(&matrix, &optimal.order), Some(optimal.cost));
+
+                    let christofides = christofides(&matrix).unwrap();
+                    assert!(christofides.cost * 2 <= optimal.cost * 3);
+                    let nearest = nearest_neighbour(&matrix, 3).unwrap();
+                    let improved = or_opt(&matrix, &two_opt(&matrix, &nearest));
+                    for tour in [christofides, nearest, improved].iter() {
+                        let mut sorted = tour.order.clone();
+                        sorted.sort();
+                        assert_eq!(sorted, (0..10).collect::<Vec<NodeLabel>>());
+                        assert_eq!(tour_cost(&matrix, &tour.order), Some(tour.cost));
+                        assert!(tour.cost >= optimal.cost);
+                    }
+                }
+
+                #[test]
+                fn test_graph_tours() {
+                    // Clockwise is cheap, anticlockwise is not
+                    let graph = Graph::new(
+                        vec![0; 3],
+                        vec![
+                            (0, 1, 1),
+                            (1, 2, 1),
+                            (2, 0, 1),
+                            (1, 0, 10),
+                            (2, 1, 10),
+                            (0, 2, 10),
+                        ],
+                    );
+                    assert_eq!(graph.held_karp_tour().unwrap().cost, 3);
+                    assert_eq!(graph.christofides_tour(), Err(TspError::Asymmetric(0, 1)));
+
+                    let path = Graph::new_unweighted(vec![0; 3], vec![(0, 1), (1, 2)]);
+                    assert_eq!(path.held_karp_tour(), Err(TspError::NoTour));
+                    assert_eq!(path.nearest_neighbour_tour(0), Err(TspError::NoTour));
+                    assert_eq!(
+                        path.nearest_neighbour_tour(3),
+                        Err(TspError::StartOutOfRange(3))
+                    );
+                    assert_eq!(path.christofides_tour(), Err(TspError::MissingEdge(1, 0)));
+                }
+            }
+        }
+
+        // Longest and critical paths through a DAG. An edge u -> v with weight w means v
+        // can start w after u starts, so the weight is usually u's duration.
+        pub mod dag {
+            use super::{Graph, NodeLabel};
+            use std::collections::VecDeque;
+            use std::error::Error;
+            use std::fmt;
+
+            // The nodes around one cycle, in order
+            #[derive(Debug, PartialEq)]
+            pub struct CycleError {
+                pub cycle: Vec<NodeLabel>,
+            }
+
+            impl fmt::Display for CycleError {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(
+                        f,
+                        "the graph is not acyclic: nodes {:?} form a cycle",
+                        self.cycle
+                    )
+                }
+            }
+
+            impl Error for CycleError {}
+
+            #[derive(Debug, PartialEq)]
+            pub struct Schedule {
+                pub earliest_start: Vec<i64>,
+                pub latest_start: Vec<i64>,
+                // How long each task can slip without delaying the end
+                pub slack: Vec<i64>,
+                pub length: i64,
+                pub critical_path: Vec<NodeLabel>,
+            }
+
+            impl<T> Graph<T> {
+                // Kahn's algorithm, lowest labels first among ready nodes
+                pub fn topological_order(&self) -> Result<Vec<NodeLabel>, CycleError> {
+                    let num_nodes = self.nodes.len();
+                    let mut in_degrees = vec![0; num_nodes];
+                    for edges in self.edges.iter() {
+                        for edge_node in edges.keys() {
+                            in_degrees[*edge_node] += 1;
+                        }
+                    }
+
+                    let mut ready: VecDeque<NodeLabel> =
+                        (0..num_nodes).filter(|n| in_degrees[*n] == 0).collect();
+                    let mut order = Vec::with_capacity(num_nodes);
+                    while let Some(node) = ready.pop_front() {
+                        order.push(node);
+                        for edge_node in self.edges[node].keys() {
+                            in_degrees[*edge_node] -= 1;
+                            if in_degrees[*edge_node] == 0 {
+                                ready.push_back(*edge_node);
+                            }
+                        }
+                    }
+
+                    if order.len() < num_nodes {
+                        return Err(CycleError {
+                            cycle: self.find_cycle(&in_degrees),
+                        });
+                    }
+                    Ok(order)
+                }
+
+                // Every node Kahn's algorithm left behind still has a predecessor that was
+                // left behind, so walking backwards through them must come round in a loop
+                fn find_cycle(&self, in_degrees: &[usize]) -> Vec<NodeLabel> {
+                    let num_nodes = self.nodes.len();
+                    let mut predecessor = vec![None; num_nodes];
+                    for (node, edges) in self.edges.iter().enumerate() {
+                        for edge_node in edges.keys() {
+                            if in_degrees[node] > 0 && predecessor[*edge_node].is_none() {
+                                predecessor[*edge_node] = Some(node);
+                            }
+                        }
+                    }
+
+                    let mut position = vec![None; num_nodes];
+                    let mut walk = Vec::new();
+                    let mut node = (0..num_nodes).find(|n| in_degrees[*n] > 0);
+                    while let Some(current) = node {
+                        if let Some(start) = position[current] {
+                            let mut cycle: Vec<NodeLabel> = walk[start..].to_vec();
+                            cycle.reverse();
+                            return cycle;
+                        }
+                        position[current] = Some(walk.len());
+                        walk.push(current);
+                        node = predecessor[current];
+                    }
+                    walk
+                }
+
+                // Heaviest path anywhere in the graph, and its weight
+                pub fn longest_path(&self) -> Result<(i64, Vec<NodeLabel>), CycleError> {
+                    let schedule = self.critical_path()?;
+                    Ok((schedule.length, schedule.critical_path))
+                }
+
+                // Critical path method: every task starts at time 0 or later, and the end is
+                // when the last task starts
+                pub fn critical_path(&self) -> Result<Schedule, CycleError> {
+                    let order = self.topological_order()?;
+                    let num_nodes = self.nodes.len();
+
+                    let mut earliest_start = vec![0; num_nodes];
+                    let mut previous = vec![None; num_nodes];
+                    for node in order.iter() {
+                        for (edge_node, weight) in self.edges[*node].iter() {
+                            let start = earliest_start[*node] + i64::from(*weight);
+                            // Ties extend the path back through zero-weight edges
+                            if start > earliest_start[*edge_node]
+                                || (start == earliest_start[*edge_node]
+                                    && previous[*edge_node].is_none())
+                            {
+                                earliest_start[*edge_node] = start;
+                                previous[*edge_node] = Some(*node);
+                            }
+                        }
+                    }
+
+                    let last = (0..num_nodes).rev().max_by_key(|n| earliest_start[*n]);
+                    let length = last.map_or(0, |n| earliest_start[n]);
+
+                    let mut latest_start = vec![length; num_nodes];
+                    for node in order.iter().rev() {
+                        for (edge_node, weight) in self.edges[*node].iter() {
+                            let start = latest_start[*edge_node] - i64::from(*weight);
+                            if start < latest_start[*node] {
+                                latest_start[*node] = start;
+                            }
+                        }
+                    }
+
+                    let slack = latest_start
+                        .iter()
+                        .zip(earliest_start.iter())
+                        .map(|(latest, earliest)| latest - earliest)
+                        .collect();
+
+                    let mut critical_path = Vec::new();
+                    let mut node = last;
+                    while let Some(current) = node {
+                        critical_path.push(current);
+                        node = previous[current];
+                    }
+                    critical_path.reverse();
+
+                    Ok(Schedule {
+                        earliest_start,
+                        latest_start,
+                        slack,
+                        length,
+                        critical_path,
+                    })
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn test_critical_path() {
+                    // A start node, tasks taking 3, 2 and 4, and an end node. The third task
+                    // needs the other two.
+                    let graph = Graph::new(
+                        vec![0; 5],
+                        vec![(0, 1, 0), (0, 2, 0), (1, 3, 3), (2, 3, 2), (3, 4, 4)],
+                    );
+                    let schedule = graph.critical_path().unwrap();
+                    assert_eq!(schedule.earliest_start, vec![0, 0, 0, 3, 7]);
+                    assert_eq!(schedule.latest_start, vec![0, 0, 1, 3, 7]);
+                    assert_eq!(schedule.slack, vec![0, 0, 1, 0, 0]);
+                    assert_eq!(schedule.length, 7);
+                    assert_eq!(schedule.critical_path, vec![0, 1, 3, 4]);
+
+                    assert_eq!(graph.longest_path(), Ok((7, vec![0, 1, 3, 4])));
+                    assert_eq!(graph.topological_order(), Ok(vec![0, 1, 2, 3, 4]));
+
+                    let empty: Graph<i32> = Graph::new(vec![], vec![]);
+                    assert_eq!(empty.longest_path(), Ok((0, vec![])));
+                }
+
+                #[test]
+                fn test_cycle_witness() {
+                    let graph = Graph::new_unweighted(
+                        vec![0; 5],
+                        vec![(3, 0), (0, 1), (1, 2), (2, 0), (2, 4)],
+                    );
+                    let cycle = graph.critical_path().unwrap_err().cycle;
+                    assert_eq!(cycle.len(), 3);
+                    for (i, node) in cycle.iter().enumerate() {
+                        let next = cycle[(i + 1) % cycle.len()];
+                        assert!(graph.edges[*node].contains_key(&next));
+                    }
+
+                    let self_loop = Graph::new_unweighted(vec![0; 2], vec![(0, 1), (1, 1)]);
+                    assert_eq!(
+                        self_loop.topological_order(),
+                        Err(CycleError { cycle: vec![1] })
+                    );
+                }
+            }
+        }
+
+        // Graphviz output, e.g. `dot -Tsvg graph.dot > graph.svg`
+        pub mod dot {
+            use super::{Graph, NodeLabel, TraceEvent};
+            use std::collections::{BTreeMap, BTreeSet, HashSet};
+            use std::fmt;
+
+            #[derive(Debug, Default)]
+            pub struct DotOptions<'a> {
+                // Label nodes with their payload as well as their id
+                pub node_labels: bool,
+                pub edge_weights: bool,
+                // A path, e.g. from bfs or dfs, to draw in red
+                pub highlight: Option<&'a [NodeLabel]>,
+            }
+
+            // Where a traced search has got to, built up from its TraceEvents
+            #[derive(Debug, Default, PartialEq)]
+            pub struct SearchFrame {
+                pub frontier: Vec<NodeLabel>,
+                pub visited: BTreeSet<NodeLabel>,
+                pub parents: BTreeMap<NodeLabel, NodeLabel>,
+                // The node most recently popped
+                pub current: Option<NodeLabel>,
+            }
+
+            impl SearchFrame {
+                pub fn new() -> SearchFrame {
+                    SearchFrame::default()
+                }
+
+                // Pops take the newest copy of a node: the top of a dfs stack, and
+                // the only copy in a bfs queue
+                pub fn apply(&mut self, event: TraceEvent) {
+                    match event {
+                        TraceEvent::Push(node) => self.frontier.push(node),
+                        TraceEvent::Pop(node) => {
+                            if let Some(i) = self.frontier.iter().rposition(|n| *n == node) {
+                                self.frontier.remove(i);
+                            }
+                            self.current = Some(node);
+                        }
+                        TraceEvent::Visit(node) => {
+                            self.visited.insert(node);
+                        }
+                        TraceEvent::Parent { node, parent } => {
+                            self.parents.insert(node, parent);
+                        }
+                    }
+                }
+            }
+
+            fn escape(label: &str) -> String {
+                label.replace('\\', "\\\\").replace('"', "\\\"")
+            }
+
+            impl<T: fmt::Display> Graph<T> {
+                pub fn to_dot(&self, options: &DotOptions) -> String {
+                    let path = options.highlight.unwrap_or(&[]);
+                    let path_nodes: HashSet<NodeLabel> = path.iter().cloned().collect();
+                    let path_edges: HashSet<(NodeLabel, NodeLabel)> =
+                        path.windows(2).map(|pair| (pair[0], pair[1])).collect();
+
+                    self.write_dot(
+                        options,
+                        |node| match path_nodes.contains(&node) {
+                            true => vec!["color=red".to_string()],
+                            false => Vec::new(),
+                        },
+                        |node, edge_node| match path_edges.contains(&(node, edge_node)) {
+                            true => vec!["color=red".to_string(), "penwidth=2".to_string()],
+                            false => Vec::new(),
+                        },
+                    )
+                }
+
+                // One frame of a search animation. The current node is red, the frontier
+                // light blue and visited nodes grey; the search tree's edges are bold.
+                pub fn to_dot_frame(&self, frame: &SearchFrame, options: &DotOptions) -> String {
+                    self.write_dot(
+                        options,
+                        |node| {
+                            let fill = if frame.current == Some(node) {
+                                "red"
+                            } else if frame.frontier.contains(&node) {
+                                "lightblue"
+                            } else if frame.visited.contains(&node) {
+                                "grey"
+                            } else {
+                                return Vec::new();
+                            };
+                            vec!["style=filled".to_string(), format!("fillcolor={}", fill)]
+                        },
+                        |node, edge_node| match frame.parents.get(&edge_node) == Some(&node) {
+                            true => vec!["penwidth=2".to_string()],
+                            false => Vec::new(),
+                        },
+                    )
+                }
+
+                fn write_dot<N, E>(
+                    &self,
+                    options: &DotOptions,
+                    node_style: N,
+                    edge_style: E,
+                ) -> String
+                where
+                    N: Fn(NodeLabel) -> Vec<String>,
+                    E: Fn(NodeLabel, NodeLabel) -> Vec<String>,
+                {
+                    let mut dot = String::from("digraph {\n");
+                    for (node, data) in self.nodes.iter().enumerate() {
+                        let mut attributes = Vec::new();
+                        if options.node_labels {
+                            attributes.push(format!(
+                                "label=\"{}\"",
+                                escape(&format!("{}: {}", node, data))
+                            ));
+                        }
+                        attributes.extend(node_style(node));
+                        dot.push_str(&format!("    {}{};\n", node, attribute_list(&attributes)));
+                    }
+                    for (node, edge_node, weight) in self.edge_list() {
+                        let mut attributes = Vec::new();
+                        if options.edge_weights {
+                            attributes.push(format!("label=\"{}\"", weight));
+                        }
+                        attributes.extend(edge_style(node, edge_node));
+                        dot.push_str(&format!(
+                            "    {} -> {}{};\n",
+                            node,
+                            edge_node,
+                            attribute_list(&attributes)
+                        ));
+                    }
+                    dot.push_str("}\n");
+                    dot
+                }
+            }
+
+            fn attribute_list(attributes: &[String]) -> String {
+                match attributes.is_empty() {
+                    true => String::new(),
+                    false => format!(" [{}]", attributes.join(", ")),
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn test_to_dot() {
+                    let graph = Graph::new(vec!["a", "say \"b\""], vec![(0, 1, 5), (1, 0, 2)]);
+                    assert_eq!(
+                        graph.to_dot(&DotOptions::default()),
+                        "digraph {\n    0;\n    1;\n    0 -> 1;\n    1 -> 0;\n}\n"
+                    );
+
+                    let options = DotOptions {
+                        node_labels: true,
+                        edge_weights: true,
+                        highlight: Some(&[0, 1]),
+                    };
+                    assert_eq!(
+                        graph.to_dot(&options),
+                        "digraph {\n    0 [label=\"0: a\", color=red];\n    \
+                         1 [label=\"1: say \\\"b\\\"\", color=red];\n    \
+                         0 -> 1 [label=\"5\", color=red, penwidth=2];\n    \
+                         1 -> 0 [label=\"2\"];\n}\n"
+                    );
+                }
+
+                #[test]
+                fn test_to_dot_frame() {
+                    let graph = Graph::new_unweighted(vec![0; 4], vec![(0, 1), (0, 2), (1, 3)]);
+                    let mut frame = SearchFrame::new();
+                    let mut frames = Vec::new();
+                    graph.bfs_traced(0, 3, |event| {
+                        frame.apply(event);
+                        frames.push(graph.to_dot_frame(&frame, &DotOptions::default()));
+                    });
+                    assert_eq!(frame.frontier, vec![]);
+                    assert_eq!(frame.current, Some(3));
+                    assert_eq!(frames.len(), 15);
+                    // Just after 3 is pushed, while 1 is being expanded
+                    assert_eq!(
+                        frames[12],
+                        "digraph {\n    \
+                         0 [style=filled, fillcolor=grey];\n    \
+                         1 [style=filled, fillcolor=red];\n    \
+                         2 [style=filled, fillcolor=lightblue];\n    \
+                         3 [style=filled, fillcolor=lightblue];\n    \
+                         0 -> 1 [penwidth=2];\n    \
+                         0 -> 2 [penwidth=2];\n    \
+                         1 -> 3 [penwidth=2];\n}\n"
+                    );
+                }
+            }
+        }
+
+        // Readers and writers for common graph file formats. Node payloads aren't stored,
+        // so read graphs have a 0 for every node.
+        pub mod formats {
+            use super::{EdgeWeight, Graph, NodeLabel};
+            use std::collections::HashMap;
+            use std::io::{self, BufRead, Read, Write};
+
+            fn invalid(line_number: usize, message: &str) -> io::Error {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", line_number, message),
+                )
+            }
+
+            fn parse<F: std::str::FromStr>(
+                field: Option<&str>,
+                line_number: usize,
+                what: &str,
+            ) -> io::Result<F> {
+                let field =
+                    field.ok_or_else(|| invalid(line_number, &format!("missing {}", what)))?;
+                field
+                    .parse()
+                    .map_err(|_| invalid(line_number, &format!("bad {} {:?}", what, field)))
+            }
+
+            // Turns a 1-based id into a NodeLabel
+            fn one_based(id: usize, num_nodes: usize, line_number: usize) -> io::Result<NodeLabel> {
+                if id == 0 || id > num_nodes {
+                    return Err(invalid(
+                        line_number,
+                        &format!("node {} is out of range", id),
+                    ));
+                }
+                Ok(id - 1)
+            }
+
+            // DIMACS shortest path: `p sp <nodes> <edges>`, then `a <u> <v> <weight>` arcs
+            // with nodes numbered from 1. Lines starting with c are comments.
+            pub fn read_dimacs<R: BufRead>(reader: R) -> io::Result<Graph<i32>> {
+                let mut num_nodes = None;
+                let mut edges = Vec::new();
+                for (index, line) in reader.lines().enumerate() {
+                    let line = line?;
+                    let line_number = index + 1;
+                    let mut fields = line.split_whitespace();
+                    match fields.next() {
+                        None | Some("c") => {}
+                        Some("p") => {
+                            if fields.next() != Some("sp") {
+                                return Err(invalid(line_number, "expected a `p sp` problem line"));
+                            }
+                            num_nodes =
+                                Some(parse::<usize>(fields.next(), line_number, "node count")?);
+                        }
+                        Some("a") => {
+                            let num_nodes = num_nodes.ok_or_else(|| {
+                                invalid(line_number, "arc before the problem line")
+                            })?;
+                            let u = parse(fields.next(), line_number, "node")?;
+                            let v = parse(fields.next(), line_number, "node")?;
+                            let weight = parse(fields.next(), line_number, "weight")?;
+                            edges.push((
+                                one_based(u, num_nodes, line_number)?,
+                                one_based(v, num_nodes, line_number)?,
+                                weight,
+                            ));
+                        }
+                        Some(other) => {
+                            return Err(invalid(
+                                line_number,
+                                &format!("unknown line type {:?}", other),
+                            ))
+                        }
+                    }
+                }
+                let num_nodes = num_nodes.ok_or_else(|| invalid(0, "no problem line"))?;
+                Ok(Graph::new(vec![0; num_nodes], edges))
+            }
+
+            pub fn write_dimacs<T, W: Write>(graph: &Graph<T>, writer: &mut W) -> io::Result<()> {
+                writeln!(writer, "p sp {} {}", graph.num_nodes(), graph.num_edges())?;
+                for (u, v, weight) in graph.edge_list() {
+                    writeln!(writer, "a {} {} {}", u + 1, v + 1, weight)?;
+                }
+                Ok(())
+            }
+
+            // Without a `# nodes` header, edge lists can't name a node past this, so one stray
+            // id can't make the reader allocate billions of nodes
+            const MAX_UNDECLARED_NODES: usize = 1 << 24;
+
+            // `<u> <v>` or `<u> <v> <weight>` per line, with nodes numbered from 0. Unweighted
+            // edges get weight 1. Lines starting with # or % are comments, except for an
+            // optional `# nodes <count>` header. Without one the graph ends at the highest
+            // node with an edge, so isolated nodes after it are lost.
+            pub fn read_edge_list<R: BufRead>(reader: R) -> io::Result<Graph<i32>> {
+                let mut edges = Vec::new();
+                let mut num_nodes = 0;
+                let mut declared_nodes = None;
+                for (index, line) in reader.lines().enumerate() {
+                    let line = line?;
+                    let line_number = index + 1;
+                    let line = line.trim();
+                    if let Some(count) = line.strip_prefix("# nodes ") {
+                        declared_nodes = Some(parse::<usize>(
+                            Some(count.trim()),
+                            line_number,
+                            "node count",
+                        )?);
+                        continue;
+                    }
+                    if line.is_empty() || line.starts_with('#') || line.starts_with('%') {
+                        continue;
+                    }
+                    let mut fields = line.split_whitespace();
+                    let u: NodeLabel = parse(fields.next(), line_number, "node")?;
+                    let v: NodeLabel = parse(fields.next(), line_number, "node")?;
+                    let weight = match fields.next() {
+                        Some(weight) => parse(Some(weight), line_number, "weight")?,
+                        None => 1,
+                    };
+                    if fields.next().is_some() {
+                        return Err(invalid(line_number, "too many fields"));
+                    }
+                    let limit = declared_nodes.unwrap_or(MAX_UNDECLARED_NODES);
+                    if let Some(node) = [u, v].iter().find(|node| **node >= limit) {
+                        let message = match declared_nodes {
+                            Some(_) => format!("node {} is out of range", node),
+                            None => {
+                                format!("node {} is out of range without a `# nodes` header", node)
+                            }
+                        };
+                        return Err(invalid(line_number, &message));
+                    }
+                    let end = u
+                        .max(v)
+                        .checked_add(1)
+                        .ok_or_else(|| invalid(line_number, "node is out of range"))?;
+                    num_nodes = num_nodes.max(end);
+                    edges.push((u, v, weight));
+                }
+                let num_nodes = match declared_nodes {
+                    // Edges before the header weren't checked against it
+                    Some(declared) if declared < num_nodes => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "the header declares {} nodes but the edges use {}",
+                                declared, num_nodes
+                            ),
+                        ))
+                    }
+                    Some(declared) => declared,
+                    None => num_nodes,
+                };
+                Ok(Graph::new(vec![0; num_nodes], edges))
+            }
+
+            pub fn write_edge_list<T, W: Write>(
+                graph: &Graph<T>,
+                writer: &mut W,
+                weighted: bool,
+            ) -> io::Result<()> {
+                writeln!(writer, "# nodes {}", graph.num_nodes())?;
+                for (u, v, weight) in graph.edge_list() {
+                    match weighted {
+                        true => writeln!(writer, "{} {} {}", u, v, weight)?,
+                        false => writeln!(writer, "{} {}", u, v)?,
+                    }
+                }
+                Ok(())
+            }
+
+            // Matrix Market coordinate format, where entry (i, j) is an edge i -> j. Symmetric
+            // matrices give edges both ways, and pattern matrices give weight 1.
+            pub fn read_matrix_market<R: BufRead>(reader: R) -> io::Result<Graph<i32>> {
+                let mut lines = reader.lines().enumerate();
+                let header = match lines.next() {
+                    Some((_, line)) => line?.to_lowercase(),
+                    None => return Err(invalid(1, "missing header")),
+                };
+                let header: Vec<&str> = header.split_whitespace().collect();
+                if header.len() != 5 || header[0] != "%%matrixmarket" || header[1] != "matrix" {
+                    return Err(invalid(1, "expected a %%MatrixMarket matrix header"));
+                }
+                if header[2] != "coordinate" {
+                    return Err(invalid(1, "only coordinate matrices are supported"));
+                }
+                let pattern = match header[3] {
+                    "pattern" => true,
+                    "integer" | "real" => false,
+                    field => return Err(invalid(1, &format!("unsupported field {:?}", field))),
+                };
+                let symmetric = match header[4] {
+                    "general" => false,
+                    "symmetric" => true,
+                    symmetry => {
+                        return Err(invalid(1, &format!("unsupported symmetry {:?}", symmetry)))
+                    }
+                };
+
+                let mut num_nodes = None;
+                let mut edges = Vec::new();
+                for (index, line) in lines {
+                    let line = line?;
+                    let line_number = index + 1;
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('%') {
+                        continue;
+                    }
+                    let mut fields = line.split_whitespace();
+                    let num_nodes = match num_nodes {
+                        Some(num_nodes) => num_nodes,
+                        None => {
+                            let rows: usize = parse(fields.next(), line_number, "row count")?;
+                            let columns: usize = parse(fields.next(), line_number, "column count")?;
+                            if rows != columns {
+                                return Err(invalid(
+                                    line_number,
+                                    "an adjacency matrix must be square",
+                                ));
+                            }
+                            num_nodes = Some(rows);
+                            continue;
+                        }
+                    };
+                    let u = one_based(
+                        parse(fields.next(), line_number, "row")?,
+                        num_nodes,
+                        line_number,
+                    )?;
+                    let v = one_based(
+                        parse(fields.next(), line_number, "column")?,
+                        num_nodes,
+                        line_number,
+                    )?;
+                    let weight = match pattern {
+                        true => 1,
+                        false => {
+                            let value: f64 = parse(fields.next(), line_number, "value")?;
+                            if value.fract() != 0.0 || value.abs() > f64::from(EdgeWeight::MAX) {
+                                return Err(invalid(
+                                    line_number,
+                                    &format!("{} is not a valid weight", value),
+                                ));
+                            }
+                            value as EdgeWeight
+                        }
+                    };
+                    edges.push((u, v, weight));
+                    if symmetric && u != v {
+                        edges.push((v, u, weight));
+                    }
+                }
+                let num_nodes = num_nodes.ok_or_else(|| invalid(0, "missing size line"))?;
+                Ok(Graph::new(vec![0; num_nodes], edges))
+            }
+
+            pub fn write_matrix_market<T, W: Write>(
+                graph: &Graph<T>,
+                writer: &mut W,
+            ) -> io::Result<()> {
+                writeln!(writer, "%%MatrixMarket matrix coordinate integer general")?;
+                writeln!(
+                    writer,
+                    "{} {} {}",
+                    graph.num_nodes(),
+                    graph.num_nodes(),
+                    graph.num_edges()
+                )?;
+                for (u, v, weight) in graph.edge_list() {
+                    writeln!(writer, "{} {} {}", u + 1, v + 1, weight)?;
+                }
+                Ok(())
+            }
+
+            enum Tag {
+                Open(String, HashMap<String, String>),
+                Close(String),
+                Text(String),
+            }
+
+            // Line numbers of the tags
+            struct Located {
+                line_number: usize,
+                tag: Tag,
+            }
+
+            fn unescape(text: &str) -> String {
+                text.replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&quot;", "\"")
+                    .replace("&apos;", "'")
+                    .replace("&amp;", "&")
+            }
+
+            fn parse_attributes(
+                text: &str,
+                line_number: usize,
+            ) -> io::Result<HashMap<String, String>> {
+                let mut attributes = HashMap::new();
+                let mut rest = text.trim();
+                while !rest.is_empty() {
+                    let equals = rest.find('=').ok_or_else(|| {
+                        invalid(line_number, &format!("bad attributes {:?}", text))
+                    })?;
+                    let name = rest[..equals].trim().to_string();
+                    let value = rest[equals + 1..].trim_start();
+                    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'');
+                    let quote = quote.ok_or_else(|| {
+                        invalid(line_number, &format!("unquoted attribute {:?}", name))
+                    })?;
+                    let end = value[1..].find(quote).ok_or_else(|| {
+                        invalid(line_number, &format!("unterminated attribute {:?}", name))
+                    })?;
+                    attributes.insert(name, unescape(&value[1..=end]));
+                    rest = value[end + 2..].trim_start();
+                }
+                Ok(attributes)
+            }
+
+            // Moves past the next `length` bytes of rest, counting the lines in them
+            fn advance(rest: &mut &str, line_number: &mut usize, length: usize) {
+                *line_number += rest[..length].matches('\n').count();
+                *rest = &rest[length..];
+            }
+
+            // Just enough of XML for GraphML: elements, attributes and text. Self-closing
+            // elements give an Open followed by a Close.
+            fn tags(text: &str) -> io::Result<Vec<Located>> {
+                let mut tags = Vec::new();
+                let mut rest = text;
+                let mut line_number = 1;
+                while let Some(start) = rest.find('<') {
+                    let before = &rest[..start];
+                    if !before.trim().is_empty() {
+                        let indent = before.len() - before.trim_start().len();
+                        tags.push(Located {
+                            line_number: line_number + before[..indent].matches('\n').count(),
+                            tag: Tag::Text(unescape(before.trim())),
+                        });
+                    }
+                    advance(&mut rest, &mut line_number, start);
+                    if rest.starts_with("<!--") {
+                        let end = rest
+                            .find("-->")
+                            .ok_or_else(|| invalid(line_number, "unterminated comment"))?;
+                        advance(&mut rest, &mut line_number, end + 3);
+                        continue;
+                    }
+                    let end = rest
+                        .find('>')
+                        .ok_or_else(|| invalid(line_number, "unterminated tag"))?;
+                    let tag = &rest[1..end];
+                    let tag_line = line_number;
+                    advance(&mut rest, &mut line_number, end + 1);
+                    if tag.starts_with('?') || tag.starts_with('!') {
+                        continue;
+                    }
+                    if let Some(name) = tag.strip_prefix('/') {
+                        tags.push(Located {
+                            line_number: tag_line,
+                            tag: Tag::Close(name.trim().to_string()),
+                        });
+                        continue;
+                    }
+                    let self_closing = tag.ends_with('/');
+                    let tag = tag.trim_end_matches('/');
+                    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+                    let name = tag[..name_end].to_string();
+                    tags.push(Located {
+                        line_number: tag_line,
+                        tag: Tag::Open(name.clone(), parse_attributes(&tag[name_end..], tag_line)?),
+                    });
+                    if self_closing {
+                        tags.push(Located {
+                            line_number: tag_line,
+                            tag: Tag::Close(name),
+                        });
+                    }
+                }
+                Ok(tags)
+            }
+
+            // The first graph in a GraphML document. Edge weights come from the edge key
+            // named weight, defaulting to 1, and undirected edges go both ways.
+            pub fn read_graphml<R: Read>(mut reader: R) -> io::Result<Graph<i32>> {
+                let mut text = String::new();
+                reader.read_to_string(&mut text)?;
+
+                let mut weight_key = None;
+                let mut default_directed = true;
+                let mut ids = HashMap::new();
+                let mut edges = Vec::new();
+                // (source, target, directed, weight) of the edge being read
+                let mut edge: Option<(String, String, bool, EdgeWeight)> = None;
+                let mut data_key: Option<String> = None;
+                let mut in_graph = false;
+
+                for Located { line_number, tag } in tags(&text)? {
+                    match tag {
+                        Tag::Open(name, attributes) => match name.as_str() {
+                            "key" => {
+                                let for_edges =
+                                    attributes.get("for").map(|f| f.as_str()) == Some("edge");
+                                let named_weight = attributes.get("attr.name").map(|n| n.as_str())
+                                    == Some("weight");
+                                if for_edges && named_weight {
+                                    weight_key = attributes.get("id").cloned();
+                                }
+                            }
+                            "graph" if !in_graph && ids.is_empty() => {
+                                in_graph = true;
+                                default_directed =
+                                    attributes.get("edgedefault").map(|d| d.as_str())
+                                        != Some("undirected");
+                            }
+                            "node" if in_graph => {
+                                let id = attributes
+                                    .get("id")
+                                    .ok_or_else(|| invalid(line_number, "node without an id"))?;
+                                let label = ids.len();
+                                ids.entry(id.clone()).or_insert(label);
+                            }
+                            "edge" if in_graph => {
+                                let source = attributes
+                                    .get("source")
+                                    .ok_or_else(|| invalid(line_number, "edge without a source"))?;
+                                let target = attributes
+                                    .get("target")
+                                    .ok_or_else(|| invalid(line_number, "edge without a target"))?;
+                                let directed = match attributes.get("directed").map(|d| d.as_str())
+                                {
+                                    Some("true") => true,
+                                    Some("false") => false,
+                                    _ => default_directed,
+                                };
+                                edge = Some((source.clone(), target.clone(), directed, 1));
+                            }
+                            "data" => data_key = attributes.get("key").cloned(),
+                            _ => {}
+                        },
+                        Tag::Text(text) => {
+                            if let (Some((_, _, _, weight)), Some(key)) =
+                                (edge.as_mut(), data_key.as_ref())
+                            {
+                                if Some(key) == weight_key.as_ref() {
+                                    *weight = text.trim().parse().map_err(|_| {
+                                        invalid(line_number, &format!("bad weight {:?}", text))
+                                    })?;
+                                }
+                            }
+                        }
+                        Tag::Close(name) => match name.as_str() {
+                            "data" => data_key = None,
+                            "graph" => in_graph = false,
+                            "edge" => {
+                                if let Some((source, target, directed, weight)) = edge.take() {
+                                    let lookup = |id: &String| {
+                                        ids.get(id).cloned().ok_or_else(|| {
+                                            invalid(
+                                                line_number,
+                                                &format!("edge to unknown node {:?}", id),
+                                            )
+                                        })
+                                    };
+                                    let (u, v) = (lookup(&source)?, lookup(&target)?);
+                                    edges.push((u, v, weight));
+                                    if !directed {
+                                        edges.push((v, u, weight));
+                                    }
+                                }
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+                Ok(Graph::new(vec![0; ids.len()], edges))
+            }
+
+            pub fn write_graphml<T, W: Write>(graph: &Graph<T>, writer: &mut W) -> io::Result<()> {
+                writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+                writeln!(
+                    writer,
+                    "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+                )?;
+                writeln!(
+                    writer,
+                    "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>"
+                )?;
+                writeln!(writer, "  <graph id=\"G\" edgedefault=\"directed\">")?;
+                for node in 0..graph.num_nodes() {
+                    writeln!(writer, "    <node id=\"n{}\"/>", node)?;
+                }
+                for (u, v, weight) in graph.edge_list() {
+                    writeln!(
+                        writer,
+                        "    <edge source=\"n{}\" target=\"n{}\"><data key=\"weight\">{}</data></edge>",
+                        u, v, weight
+                    )?;
+                }
+                writeln!(writer, "  </graph>")?;
+                writeln!(writer, "</graphml>")
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                // Node 4 has no edges
+                fn example() -> Graph<i32> {
+                    Graph::new(
+                        vec![0; 5],
+                        vec![(0, 1, 3), (1, 2, 1), (2, 0, 7), (2, 3, -2)],
+                    )
+                }
+
+                fn round_trip<F, G>(write: F, read: G)
+                where
+                    F: Fn(&Graph<i32>, &mut Vec<u8>) -> io::Result<()>,
+                    G: Fn(&[u8]) -> io::Result<Graph<i32>>,
+                {
+                    let graph = example();
+                    let mut buffer = Vec::new();
+                    write(&graph, &mut buffer).unwrap();
+                    let read_back = read(&buffer).unwrap();
+                    assert_eq!(read_back.num_nodes(), graph.num_nodes());
+                    assert_eq!(read_back.edge_list(), graph.edge_list());
+                }
+
+                #[test]
+                fn test_round_trips() {
+                    round_trip(write_dimacs, |r| read_dimacs(r));
+                    round_trip(|g, w| write_edge_list(g, w, true), |r| read_edge_list(r));
+                    round_trip(write_matrix_market, |r| read_matrix_market(r));
+                    round_trip(write_graphml, |r| read_graphml(r));
+                }
+
+                #[test]
+                fn test_readers() {
+                    let dimacs = "c example\np sp 3 2\na 1 2 5\na 3 1 2\n";
+                    let graph = read_dimacs(dimacs.as_bytes()).unwrap();
+                    assert_eq!(graph.edge_list(), vec![(0, 1, 5), (2, 0, 2)]);
+                    assert!(read_dimacs("p sp 2 1\na 1 3 5\n".as_bytes()).is_err());
+
+                    let edge_list = "# comment\n0 1\n1 3 4\n";
+                    let graph = read_edge_list(edge_list.as_bytes()).unwrap();
+                    assert_eq!(graph.num_nodes(), 4);
+                    assert_eq!(graph.edge_list(), vec![(0, 1, 1), (1, 3, 4)]);
+                    assert!(read_edge_list("0 x\n".as_bytes()).is_err());
+                    let header = "# nodes 6\n0 1\n";
+                    assert_eq!(read_edge_list(header.as_bytes()).unwrap().num_nodes(), 6);
+                    let error = read_edge_list("0 4000000000\n".as_bytes()).err().unwrap();
+                    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                    let error = read_edge_list(format!("0 {}\n", usize::MAX).as_bytes())
+                        .err()
+                        .unwrap();
+                    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                    let error = read_edge_list("0 3\n# nodes 2\n".as_bytes()).err().unwrap();
+                    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                    let error = read_edge_list("# nodes 2\n0 5\n".as_bytes()).err().unwrap();
+                    assert_eq!(error.to_string(), "line 2: node 5 is out of range");
+
+                    let matrix = "%%MatrixMarket matrix coordinate pattern symmetric\n% c\n3 3 2\n2 1\n3 3\n";
+                    let graph = read_matrix_market(matrix.as_bytes()).unwrap();
+                    assert_eq!(graph.edge_list(), vec![(0, 1, 1), (1, 0, 1), (2, 2, 1)]);
+                    let real = "%%MatrixMarket matrix coordinate real general\n2 2 1\n1 2 2.5\n";
+                    assert!(read_matrix_market(real.as_bytes()).is_err());
+
+                    let graphml = r#"<?xml version="1.0"?>
+                        <graphml>
+                          <key id="w" for="edge" attr.name="weight" attr.type="int"/>
+                          <graph edgedefault="undirected">
+                            <!-- a comment -->
+                            <node id="a"/><node id="b"></node><node id="c"/>
+                            <edge source="a" target="b"><data key="w">4</data></edge>
+                            <edge source="c" target="a" directed="true"/>
+                          </graph>
+                        </graphml>"#;
+                    let graph = read_graphml(graphml.as_bytes()).unwrap();
+                    assert_eq!(graph.edge_list(), vec![(0, 1, 4), (1, 0, 4), (2, 0, 1)]);
+                    let unknown = r#"<graphml><graph><node id="a"/><edge source="a" target="b"/></graph></graphml>"#;
+                    assert!(read_graphml(unknown.as_bytes()).is_err());
+                    let no_id = "<graphml>\n  <graph>\n    <node/>\n  </graph>\n</graphml>";
+                    let error = read_graphml(no_id.as_bytes()).err().unwrap();
+                    assert_eq!(error.to_string(), "line 3: node without an id");
+                    let bad_weight = "<graphml><key id=\"w\" for=\"edge\" attr.name=\"weight\"/>\n<graph><node id=\"a\"/>\n<edge source=\"a\" target=\"a\"><data key=\"w\">\n  x</data></edge>\n</graph></graphml>";
+                    let error = read_graphml(bad_weight.as_bytes()).err().unwrap();
+                    assert_eq!(error.to_string(), "line 4: bad weight \"x\"");
+                }
+            }
+        }
+
+        // The native graph file format. An optional `directed` or `undirected` line, then a
+        // node count, a bracketed edge list on one line, and the source and destination
+        // nodes. Edges are [u, v] or [u, v, weight], and undirected edges go both ways.
+        // Any lines after that give node payloads as `node: payload`, e.g.
+        //
+        //     undirected
+        //     3
+        //     [[0, 1], [1, 2, 5]]
+        //     0
+        //     2
+        //     0: start
+        //     2: end
+        //
+        // Named graphs have one statement per line instead. Blank lines and lines starting
+        // with # are skipped.
+        //
+        //     A -> B        an edge from A to B with weight 1
+        //     B -> C 5      an edge with weight 5
+        //     C -- D        edges both ways
+        //     E             a node with no edges
+        //     source A
+        //     destination D
+        pub mod io {
+            use super::named::NamedGraph;
+            use super::{EdgeWeight, Graph, NodeLabel};
+            use std::error::Error;
+            use std::fmt;
+            use std::io::{self, BufRead, BufReader, Read, Write};
+            use std::str::FromStr;
+
+            pub struct NamedGraphFile<T> {
+                pub graph: NamedGraph<String, T>,
+                pub source: NodeLabel,
+                pub destination: NodeLabel,
+            }
+
+            pub struct GraphFile<T> {
+                pub graph: Graph<T>,
+                pub source: NodeLabel,
+                pub destination: NodeLabel,
+                pub directed: bool,
+            }
+
+            #[derive(Debug, PartialEq)]
+            pub enum ParseErrorKind {
+                Io(String),
+                // Which line was expected
+                MissingLine(&'static str),
+                // Which character was expected
+                Expected(char),
+                BadNumber(String),
+                // An edge with no target
+                OddEdgeList,
+                // An edge with this many numbers instead of 2 or 3
+                BadEdge(usize),
+                NodeOutOfRange(NodeLabel),
+                BadPayload(String),
+                DuplicatePayload(NodeLabel),
+                // A named graph line that isn't an edge, node, source or destination
+                BadStatement,
+                UnknownNode(String),
+            }
+
+            // Lines and columns count from 1
+            #[derive(Debug, PartialEq)]
+            pub struct GraphParseError {
+                pub line: usize,
+                pub column: usize,
+                pub kind: ParseErrorKind,
+            }
+
+            impl fmt::Display for GraphParseError {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "line {}, column {}: ", self.line, self.column)?;
+                    match &self.kind {
+                        ParseErrorKind::Io(message) => write!(f, "{}", message),
+                        ParseErrorKind::MissingLine(what) => write!(f, "expected the {}", what),
+                        ParseErrorKind::Expected(c) => write!(f, "expected {:?}", c),
+                        ParseErrorKind::BadNumber(token) => {
+                            write!(f, "{:?} is not a valid number", token)
+                        }
+                        ParseErrorKind::OddEdgeList => write!(f, "edge has a source but no target"),
+                        ParseErrorKind::BadEdge(count) => {
+                            write!(f, "edge has {} numbers, expected 2 or 3", count)
+                        }
+                        ParseErrorKind::NodeOutOfRange(node) => {
+                            write!(f, "node {} is not in the graph", node)
+                        }
+                        ParseErrorKind::BadPayload(payload) => {
+                            write!(f, "{:?} is not a valid node payload", payload)
+                        }
+                        ParseErrorKind::DuplicatePayload(node) => {
+                            write!(f, "node {} already has a payload", node)
+                        }
+                        ParseErrorKind::BadStatement => write!(
+                            f,
+                            "expected `A -> B`, `A -- B`, a node name, source or destination"
+                        ),
+                        ParseErrorKind::UnknownNode(name) => {
+                            write!(f, "there is no node {:?}", name)
+                        }
+                    }
+                }
+            }
+
+            impl Error for GraphParseError {}
+
+            fn error(line: usize, column: usize, kind: ParseErrorKind) -> GraphParseError {
+                GraphParseError { line, column, kind }
+            }
+
+            // Hands out lines one at a time, remembering where we are
+            struct Lines<R> {
+                lines: io::Lines<R>,
+                line_number: usize,
+            }
+
+            impl<R: BufRead> Lines<R> {
+                fn next(&mut self, what: &'static str) -> Result<String, GraphParseError> {
+                    match self.next_line() {
+                        Some(line) => line,
+                        None => Err(error(
+                            self.line_number + 1,
+                            1,
+                            ParseErrorKind::MissingLine(what),
+                        )),
+                    }
+                }
+
+                fn next_line(&mut self) -> Option<Result<String, GraphParseError>> {
+                    let line = self.lines.next()?;
+                    self.line_number += 1;
+                    Some(
+                        line.map_err(|e| {
+                            error(self.line_number, 1, ParseErrorKind::Io(e.to_string()))
+                        }),
+                    )
+                }
+            }
+
+            // Walks along one line, counting columns in characters
+            struct Cursor<'a> {
+                chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+                text: &'a str,
+                line: usize,
+                column: usize,
+            }
+
+            impl<'a> Cursor<'a> {
+                fn new(text: &'a str, line: usize) -> Cursor<'a> {
+                    Cursor {
+                        chars: text.char_indices().peekable(),
+                        text,
+                        line,
+                        column: 1,
+                    }
+                }
+
+                fn skip_whitespace(&mut self) {
+                    while self.chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+                        self.chars.next();
+                        self.column += 1;
+                    }
+                }
+
+                // The next non-whitespace character, without consuming it
+                fn peek(&mut self) -> Option<char> {
+                    self.skip_whitespace();
+                    self.chars.peek().map(|(_, c)| *c)
+                }
+
+                fn expect(&mut self, expected: char) -> Result<(), GraphParseError> {
+                    match self.peek() {
+                        Some(c) if c == expected => {
+                            self.chars.next();
+                            self.column += 1;
+                            Ok(())
+                        }
+                        _ => Err(self.error(ParseErrorKind::Expected(expected))),
+                    }
+                }
+
+                // Everything up to the next separator, and the column it started at
+                fn token(&mut self) -> (usize, &'a str) {
+                    self.skip_whitespace();
+                    let column = self.column;
+                    let start = self
+                        .chars
+                        .peek()
+                        .map_or(self.text.len(), |(index, _)| *index);
+                    let mut end = start;
+                    while let Some((index, c)) = self.chars.peek() {
+                        if c.is_whitespace() || *c == '[' || *c == ']' || *c == ',' {
+                            break;
+                        }
+                        end = index + c.len_utf8();
+                        self.chars.next();
+                        self.column += 1;
+                    }
+                    (column, &self.text[start..end])
+                }
+
+                fn number<F: FromStr>(&mut self) -> Result<(usize, F), GraphParseError> {
+                    let (column, token) = self.token();
+                    match token.parse() {
+                        Ok(number) => Ok((column, number)),
+                        Err(_) => Err(error(
+                            self.line,
+                            column,
+                            ParseErrorKind::BadNumber(token.to_string()),
+                        )),
+                    }
+                }
+
+                fn node(&mut self, num_nodes: usize) -> Result<NodeLabel, GraphParseError> {
+                    let (column, node) = self.number()?;
+                    if node >= num_nodes {
+                        return Err(error(
+                            self.line,
+                            column,
+                            ParseErrorKind::NodeOutOfRange(node),
+                        ));
+                    }
+                    Ok(node)
+                }
+
+                fn error(&self, kind: ParseErrorKind) -> GraphParseError {
+                    error(self.line, self.column, kind)
+                }
+
+                fn finish(&mut self) -> Result<(), GraphParseError> {
+                    match self.peek() {
+                        Some(_) => {
+                            let (column, token) = self.token();
+                            Err(error(
+                                self.line,
+                                column,
+                                ParseErrorKind::BadNumber(token.to_string()),
+                            ))
+                        }
+                        None => Ok(()),
+                    }
+                }
+            }
+
+            // One [u, v] or [u, v, weight]
+            fn edge(
+                cursor: &mut Cursor,
+                num_nodes: usize,
+            ) -> Result<(NodeLabel, NodeLabel, EdgeWeight), GraphParseError> {
+                let (line, start) = (cursor.line, cursor.column);
+                cursor.expect('[')?;
+                let mut fields = Vec::new();
+                loop {
+                    fields.push(cursor.token());
+                    match cursor.peek() {
+                        Some(',') => cursor.expect(',')?,
+                        _ => break,
+                    }
+                }
+                cursor.expect(']')?;
+
+                let node = |(column, token): (usize, &str)| match token.parse() {
+                    Ok(node) if node < num_nodes => Ok(node),
+                    Ok(node) => Err(error(line, column, ParseErrorKind::NodeOutOfRange(node))),
+                    Err(_) => Err(error(
+                        line,
+                        column,
+                        ParseErrorKind::BadNumber(token.to_string()),
+                    )),
+                };
+                match fields.len() {
+                    1 => {
+                        node(fields[0])?;
+                        Err(error(line, fields[0].0, ParseErrorKind::OddEdgeList))
+                    }
+                    2 | 3 => {
+                        let weight = match fields.get(2) {
+                            Some((column, token)) => token.parse().map_err(|_| {
+                                error(line, *column, ParseErrorKind::BadNumber(token.to_string()))
+                            })?,
+                            None => 1,
+                        };
+                        Ok((node(fields[0])?, node(fields[1])?, weight))
+                    }
+                    count => Err(error(line, start, ParseErrorKind::BadEdge(count))),
+                }
+            }
+
+            fn edge_list(
+                text: &str,
+                line: usize,
+                num_nodes: usize,
+            ) -> Result<Vec<(NodeLabel, NodeLabel, EdgeWeight)>, GraphParseError> {
+                let mut cursor = Cursor::new(text, line);
+                let mut edges = Vec::new();
+                cursor.expect('[')?;
+                if cursor.peek() != Some(']') {
+                    loop {
+                        edges.push(edge(&mut cursor, num_nodes)?);
+                        match cursor.peek() {
+                            Some(',') => cursor.expect(',')?,
+                            _ => break,
+                        }
+                    }
+                }
+                cursor.expect(']')?;
+                cursor.finish()?;
+                Ok(edges)
+            }
+
+            // Streams the native format from any reader. Nodes without a payload get
+            // T::default().
+            pub fn read_graph<T, R>(reader: R) -> Result<GraphFile<T>, GraphParseError>
+            where
+                T: FromStr + Default,
+                R: Read,
+            {
+                let mut lines = Lines {
+                    lines: BufReader::new(reader).lines(),
+                    line_number: 0,
+                };
+
+                let mut line = lines.next("node count")?;
+                let directed = match line.trim() {
+                    "directed" => Some(true),
+                    "undirected" => Some(false),
+                    _ => None,
+                };
+                if directed.is_some() {
+                    line = lines.next("node count")?;
+                }
+                let directed = directed.unwrap_or(true);
+
+                let mut cursor = Cursor::new(&line, lines.line_number);
+                let (_column, num_nodes) = cursor.number::<usize>()?;
+                cursor.finish()?;
+
+                let line = lines.next("edge list")?;
+                let mut edges = edge_list(&line, lines.line_number, num_nodes)?;
+                if !directed {
+                    let reversed: Vec<(NodeLabel, NodeLabel, EdgeWeight)> = edges
+                        .iter()
+                        .map(|(u, v, weight)| (*v, *u, *weight))
+                        .collect();
+                    edges.extend(reversed);
+                }
+
+                let mut endpoints = Vec::new();
+                for what in ["source", "destination"].iter() {
+                    let line = lines.next(what)?;
+                    let mut cursor = Cursor::new(&line, lines.line_number);
+                    endpoints.push(cursor.node(num_nodes)?);
+                    cursor.finish()?;
+                }
+
+                let mut payloads: Vec<Option<T>> = (0..num_nodes).map(|_| None).collect();
+                while let Some(line) = lines.next_line() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let line_number = lines.line_number;
+                    let colon = line.find(':').ok_or_else(|| {
+                        let column = line.trim_end().chars().count() + 1;
+                        error(line_number, column, ParseErrorKind::Expected(':'))
+                    })?;
+
+                    let mut cursor = Cursor::new(&line[..colon], line_number);
+                    let (column, node) = cursor.number()?;
+                    if node >= num_nodes {
+                        return Err(error(
+                            line_number,
+                            column,
+                            ParseErrorKind::NodeOutOfRange(node),
+                        ));
+                    }
+                    cursor.finish()?;
+                    if payloads[node].is_some() {
+                        return Err(error(
+                            line_number,
+                            column,
+                            ParseErrorKind::DuplicatePayload(node),
+                        ));
+                    }
+
+                    let text = line[colon + 1..].trim();
+                    let column = line[..=colon].chars().count() + 1;
+                    let payload = text.parse().map_err(|_| {
+                        error(
+                            line_number,
+                            column,
+                            ParseErrorKind::BadPayload(text.to_string()),
+                        )
+                    })?;
+                    payloads[node] = Some(payload);
+                }
+
+                let nodes = payloads
+                    .into_iter()
+                    .map(|p| p.unwrap_or_default())
+                    .collect();
+                Ok(GraphFile {
+                    graph: Graph::new(nodes, edges),
+                    source: endpoints[0],
+                    destination: endpoints[1],
+                    directed,
+                })
+            }
+
+            // Whitespace separated words and the columns they start at
+            fn words(line: &str) -> Vec<(usize, &str)> {
+                let mut words = Vec::new();
+                let mut start = None;
+                let mut column = 0;
+                for (index, c) in line
+                    .char_indices()
+                    .chain(std::iter::once((line.len(), ' ')))
+                {
+                    column += 1;
+                    match (start, c.is_whitespace()) {
+                        (None, false) => start = Some((index, column)),
+                        (Some((begin, begin_column)), true) => {
+                            words.push((begin_column, &line[begin..index]));
+                            start = None;
+                        }
+                        _ => {}
+                    }
+                }
+                words
+            }
+
+            // Streams a named graph from any reader. Nodes get T::default() as their payload.
+            pub fn read_named_graph<T, R>(reader: R) -> Result<NamedGraphFile<T>, GraphParseError>
+            where
+                T: Default,
+                R: Read,
+            {
+                let mut lines = Lines {
+                    lines: BufReader::new(reader).lines(),
+                    line_number: 0,
+                };
+                let mut graph = NamedGraph::new();
+                // Where each endpoint was named, so unknown names can be pointed at
+                let mut source = None;
+                let mut destination = None;
+
+                while let Some(line) = lines.next_line() {
+                    let line = line?;
+                    let line_number = lines.line_number;
+                    let words = words(&line);
+                    match words.as_slice() {
+                        [] => {}
+                        [(_, word), ..] if word.starts_with('#') => {}
+                        [(column, "source"), (_, name)] => {
+                            source = Some((line_number, *column, name.to_string()))
+                        }
+                        [(column, "destination"), (_, name)] => {
+                            destination = Some((line_number, *column, name.to_string()))
+                        }
+                        [(_, name)] => {
+                            graph.intern(name.to_string());
+                        }
+                        [(_, from), (_, arrow), (_, to), weight @ ..]
+                            if *arrow == "->" || *arrow == "--" =>
+                        {
+                            let weight = match weight {
+                                [] => 1,
+                                [(column, token)] => token.parse().map_err(|_| {
+                                    error(
+                                        line_number,
+                                        *column,
+                                        ParseErrorKind::BadNumber(token.to_string()),
+                                    )
+                                })?,
+                                [_, (column, _), ..] => {
+                                    return Err(error(
+                                        line_number,
+                                        *column,
+                                        ParseErrorKind::BadStatement,
+                                    ))
+                                }
+                            };
+                            graph.add_edge(from.to_string(), to.to_string(), weight);
+                            if *arrow == "--" {
+                                graph.add_edge(to.to_string(), from.to_string(), weight);
+                            }
+                        }
+                        [(column, _), ..] => {
+                            return Err(error(line_number, *column, ParseErrorKind::BadStatement))
+                        }
+                    }
+                }
+
+                let endpoint = |named: Option<(usize, usize, String)>, what| match named {
+                    Some((line, column, name)) => graph
+                        .label(&name)
+                        .ok_or_else(|| error(line, column, ParseErrorKind::UnknownNode(name))),
+                    None => Err(error(
+                        lines.line_number + 1,
+                        1,
+                        ParseErrorKind::MissingLine(what),
+                    )),
+                };
+                let source = endpoint(source, "source")?;
+                let destination = endpoint(destination, "destination")?;
+                Ok(NamedGraphFile {
+                    graph,
+                    source,
+                    destination,
+                })
+            }
+
+            // The inverse of read_graph. Weights are written when any edge has a weight other
+            // than 1, and payloads when they differ from T::default(). Undirected files list
+            // each edge once from its lower endpoint, so the graph should hold both directions
+            // with the same weight.
+            pub fn write_graph<T, W>(file: &GraphFile<T>, writer: &mut W) -> io::Result<()>
+            where
+                T: fmt::Display + Default + PartialEq,
+                W: Write,
+            {
+                let edge_list: Vec<(NodeLabel, NodeLabel, EdgeWeight)> = file
+                    .graph
+                    .edge_list()
+                    .into_iter()
+                    .filter(|(head, tail, _weight)| file.directed || head <= tail)
+                    .collect();
+                let weighted = edge_list.iter().any(|(_head, _tail, weight)| *weight != 1);
+                let edges: Vec<String> = edge_list
+                    .iter()
+                    .map(|(head, tail, weight)| match weighted {
+                        true => format!("[{}, {}, {}]", head, tail, weight),
+                        false => format!("[{}, {}]", head, tail),
+                    })
+                    .collect();
+                let directive = match file.directed {
+                    true => "directed",
+                    false => "undirected",
+                };
+                write!(
+                    writer,
+                    "{}\n{}\n[{}]\n{}\n{}\n",
+                    directive,
+                    file.graph.num_nodes(),
+                    edges.join(", "),
+                    file.source,
+                    file.destination
+                )?;
+
+                let default = T::default();
+                for (node, payload) in file.graph.nodes.iter().enumerate() {
+                    if *payload != default {
+                        writeln!(writer, "{}: {}", node, payload)?;
+                    }
+                }
+                Ok(())
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                fn parse_error(text: &str) -> GraphParseError {
+                    match read_graph::<i32, _>(text.as_bytes()) {
+                        Ok(_) => panic!("{:?} should not parse", text),
+                        Err(e) => e,
+                    }
+                }
+
+                #[test]
+                fn test_read_graph() {
+                    let file: GraphFile<i32> =
+                        read_graph(include_str!("../tests/graph_search_1.txt").as_bytes()).unwrap();
+                    assert_eq!(file.graph.num_nodes(), 10);
+                    assert!(file.directed);
+                    assert_eq!(
+                        file.graph.bfs(file.source, file.destination),
+                        Some(vec![0, 1, 2, 4])
+                    );
+
+                    let file: GraphFile<i32> =
+                        read_graph(include_str!("../tests/graph_search_3.txt").as_bytes()).unwrap();
+                    assert_eq!(
+                        file.graph.bfs(file.source, file.destination),
+                        Some(vec![36, 75, 76, 77])
+                    );
+
+                    let mut written = Vec::new();
+                    write_graph(&file, &mut written).unwrap();
+                    let file: GraphFile<i32> = read_graph(&written[..]).unwrap();
+                    assert_eq!(
+                        file.graph.bfs(file.source, file.destination),
+                        Some(vec![36, 75, 76, 77])
+                    );
+                }
+
+                #[test]
+                fn test_weights_and_payloads() {
+                    let text =
+                        "undirected\n3\n[[0, 1], [1, 2, -5]]\n0\n2\n\n0: start\n2: the end\n";
+                    let file: GraphFile<String> = read_graph(text.as_bytes()).unwrap();
+                    assert!(!file.directed);
+                    assert_eq!(
+                        file.graph.edge_list(),
+                        vec![(0, 1, 1), (1, 0, 1), (1, 2, -5), (2, 1, -5)]
+                    );
+                    assert_eq!(file.graph.nodes, vec!["start", "", "the end"]);
+
+                    let mut written = Vec::new();
+                    write_graph(&file, &mut written).unwrap();
+                    assert_eq!(
+                        String::from_utf8(written.clone()).unwrap(),
+                        "undirected\n3\n[[0, 1, 1], [1, 2, -5]]\n0\n2\n0: start\n2: the end\n"
+                    );
+                    let read_back: GraphFile<String> = read_graph(&written[..]).unwrap();
+                    assert!(!read_back.directed);
+                    assert_eq!(read_back.graph.edge_list(), file.graph.edge_list());
+                    assert_eq!(read_back.graph.nodes, file.graph.nodes);
+
+                    let file: GraphFile<i32> =
+                        read_graph("directed\n2\n[]\n0\n1\n1: 7\n".as_bytes()).unwrap();
+                    assert_eq!(file.graph.nodes, vec![0, 7]);
+                }
+
+                #[test]
+                fn test_read_named_graph() {
+                    let text =
+                        "# roads\nA -> B\nB -> C 5\n\nC -- D 2\nE\nsource A\ndestination D\n";
+                    let file: NamedGraphFile<i32> = read_named_graph(text.as_bytes()).unwrap();
+                    let graph = &file.graph;
+                    assert_eq!(graph.graph().num_nodes(), 5);
+                    assert_eq!(
+                        graph.graph().edge_list(),
+                        vec![(0, 1, 1), (1, 2, 5), (2, 3, 2), (3, 2, 2)]
+                    );
+                    assert_eq!(graph.keys(&[file.source, file.destination]), vec!["A", "D"]);
+                    let path = graph.bfs(&"A".to_string(), &"D".to_string()).unwrap();
+                    assert_eq!(path, vec!["A", "B", "C", "D"]);
+
+                    let named_error = |text: &str| match read_named_graph::<i32, _>(text.as_bytes())
+                    {
+                        Ok(_) => panic!("{:?} should not parse", text),
+                        Err(e) => e,
+                    };
+                    assert_eq!(
+                        named_error("A -> B x\n"),
+                        error(1, 8, ParseErrorKind::BadNumber("x".to_string()))
+                    );
+                    assert_eq!(
+                        named_error("A => B\n"),
+                        error(1, 1, ParseErrorKind::BadStatement)
+                    );
+                    assert_eq!(
+                        named_error("A -> B\nsource A\n"),
+                        error(3, 1, ParseErrorKind::MissingLine("destination"))
+                    );
+                    assert_eq!(
+                        named_error("A -> B\nsource A\ndestination  Z\n"),
+                        error(3, 1, ParseErrorKind::UnknownNode("Z".to_string()))
+                    );
+                }
+
+                #[test]
+                fn test_parse_errors() {
+                    assert_eq!(
+                        parse_error(""),
+                        error(1, 1, ParseErrorKind::MissingLine("node count"))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[[0, 1]]\n0\n"),
+                        error(4, 1, ParseErrorKind::MissingLine("destination"))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[[0, 1], [1, x2]]\n0\n2\n"),
+                        error(2, 14, ParseErrorKind::BadNumber("x2".to_string()))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[[0, 1], [1]]\n0\n2\n"),
+                        error(2, 11, ParseErrorKind::OddEdgeList)
+                    );
+                    assert_eq!(
+                        parse_error("3\n[[0, 1, 2, 3]]\n0\n2\n"),
+                        error(2, 2, ParseErrorKind::BadEdge(4))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[[0, 1]\n0\n2\n"),
+                        error(2, 8, ParseErrorKind::Expected(']'))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[[0, 3]]\n0\n2\n"),
+                        error(2, 6, ParseErrorKind::NodeOutOfRange(3))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[]\n 5\n2\n"),
+                        error(3, 2, ParseErrorKind::NodeOutOfRange(5))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[]\n0\n-2\n"),
+                        error(4, 1, ParseErrorKind::BadNumber("-2".to_string()))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[]\n0\n2\n0: x\n"),
+                        error(5, 3, ParseErrorKind::BadPayload("x".to_string()))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[]\n0\n2\n1: 4\n1: 5\n"),
+                        error(6, 1, ParseErrorKind::DuplicatePayload(1))
+                    );
+                    assert_eq!(
+                        parse_error("3\n[]\n0\n2\n7\n").to_string(),
+                        "line 5, column 2: expected ':'"
+                    );
+                }
+            }
+        }
+
+        // Graphs keyed by names, or any other Hash + Eq key, instead of dense labels. The
+        // wrapped Graph runs the algorithms. Searches, components, cliques and centrality
+        // have wrappers that take and return keys. For everything else, like dominators,
+        // trees, cuts, colouring or TSP, translate at the edges: label and labels_of going
+        // in, then key, keys, groups or by_key on what comes out of graph().
+        pub mod named {
+            use super::{EdgeWeight, Graph, NegativeWeight, NodeLabel};
+            use std::borrow::Borrow;
+            use std::collections::{BTreeMap, HashMap};
+            use std::hash::Hash;
+
+            pub struct NamedGraph<K, T> {
+                graph: Graph<T>,
+                keys: Vec<K>,
+                labels: HashMap<K, NodeLabel>,
+            }
+
+            impl<K: Hash + Eq + Clone, T> Default for NamedGraph<K, T> {
+                fn default() -> Self {
+                    NamedGraph::new()
+                }
+            }
+
+            impl<K: Hash + Eq + Clone, T> NamedGraph<K, T> {
+                pub fn new() -> NamedGraph<K, T> {
+                    NamedGraph {
+                        graph: Graph {
+                            nodes: Vec::new(),
+                            edges: Vec::new(),
+                        },
+                        keys: Vec::new(),
+                        labels: HashMap::new(),
+                    }
+                }
+
+                // Names the nodes of an existing graph, keys[label] for each label
+                pub fn from_graph(graph: Graph<T>, keys: Vec<K>) -> NamedGraph<K, T> {
+                    assert_eq!(
+                        graph.num_nodes(),
+                        keys.len(),
+                        "every node needs exactly one key"
+                    );
+                    let labels: HashMap<K, NodeLabel> = keys.iter().cloned().zip(0..).collect();
+                    assert_eq!(labels.len(), keys.len(), "keys must be unique");
+                    NamedGraph {
+                        graph,
+                        keys,
+                        labels,
+                    }
+                }
+
+                // Nodes appear in the order their keys are first seen
+                pub fn from_edges(edges: Vec<(K, K, EdgeWeight)>) -> NamedGraph<K, T>
+                where
+                    T: Default,
+                {
+                    let mut graph = NamedGraph::new();
+                    for (from, to, weight) in edges {
+                        graph.add_edge(from, to, weight);
+                    }
+                    graph
+                }
+
+                // Adds the node, or replaces its payload if the key is already there
+                pub fn add_node(&mut self, key: K, payload: T) -> NodeLabel {
+                    if let Some(label) = self.labels.get(&key) {
+                        self.graph.nodes[*label] = payload;
+                        return *label;
+                    }
+                    let label = self.keys.len();
+                    self.graph.nodes.push(payload);
+                    self.graph.edges.push(BTreeMap::new());
+                    self.keys.push(key.clone());
+                    self.labels.insert(key, label);
+                    label
+                }
+
+                // Unknown keys become nodes with a default payload
+                pub fn intern(&mut self, key: K) -> NodeLabel
+                where
+                    T: Default,
+                {
+                    match self.labels.get(&key) {
+                        Some(label) => *label,
+                        None => self.add_node(key, T::default()),
+                    }
+                }
+
+                // Replaces the weight if the edge is already there
+                pub fn add_edge(&mut self, from: K, to: K, weight: EdgeWeight)
+                where
+                    T: Default,
+                {
+                    let from = self.intern(from);
+                    let to = self.intern(to);
+                    self.graph.edges[from].insert(to, weight);
+                }
+
+                pub fn remove_edge<Q>(&mut self, from: &Q, to: &Q) -> Option<EdgeWeight>
+                where
+                    K: Borrow<Q>,
+                    Q: Hash + Eq + ?Sized,
+                {
+                    let (from, to) = (self.label(from)?, self.label(to)?);
+                    self.graph.edges[from].remove(&to)
+                }
+
+                pub fn graph(&self) -> &Graph<T> {
+                    &self.graph
+                }
+
+                pub fn into_parts(self) -> (Graph<T>, Vec<K>) {
+                    (self.graph, self.keys)
+                }
+
+                pub fn label<Q>(&self, key: &Q) -> Option<NodeLabel>
+                where
+                    K: Borrow<Q>,
+                    Q: Hash + Eq + ?Sized,
+                {
+                    self.labels.get(key).cloned()
+                }
+
+                pub fn key(&self, label: NodeLabel) -> Option<&K> {
+                    self.keys.get(label)
+                }
+
+                // None if any key is unknown
+                pub fn labels_of<Q>(&self, keys: &[&Q]) -> Option<Vec<NodeLabel>>
+                where
+                    K: Borrow<Q>,
+                    Q: Hash + Eq + ?Sized,
+                {
+                    keys.iter().map(|key| self.label(*key)).collect()
+                }
+
+                // Translates a path or set of labels back to keys
+                pub fn keys(&self, labels: &[NodeLabel]) -> Vec<&K> {
+                    labels.iter().map(|label| &self.keys[*label]).collect()
+                }
+
+                // For results with one entry per group of nodes, like components or cliques
+                pub fn groups(&self, groups: &[Vec<NodeLabel>]) -> Vec<Vec<&K>> {
+                    groups.iter().map(|group| self.keys(group)).collect()
+                }
+
+                // For results with one value per node, like centrality scores or colours
+                pub fn by_key<V>(&self, values: Vec<V>) -> Vec<(&K, V)> {
+                    self.keys.iter().zip(values).collect()
+                }
+
+                // None if either key is unknown or there is no path
+                pub fn bfs(&self, source: &K, destination: &K) -> Option<Vec<&K>> {
+                    let path = self
+                        .graph
+                        .bfs(self.label(source)?, self.label(destination)?)?;
+                    Some(self.keys(&path))
+                }
+
+                pub fn dfs(&self, source: &K, destination: &K) -> Option<Vec<&K>> {
+                    let path = self
+                        .graph
+                        .dfs(self.label(source)?, self.label(destination)?)?;
+                    Some(self.keys(&path))
+                }
+
+                // Ok(None) if either key is unknown or there is no path
+                pub fn dijkstra(
+                    &self,
+                    source: &K,
+                    destination: &K,
+                ) -> Result<Option<Vec<&K>>, NegativeWeight> {
+                    let (source, destination) = match (self.label(source), self.label(destination))
+                    {
+                        (Some(source), Some(destination)) => (source, destination),
+                        _ => return Ok(None),
+                    };
+                    let path = self.graph.dijkstra(source, destination)?;
+                    Ok(path.map(|path| self.keys(&path)))
+                }
+
+                pub fn components(&self) -> Vec<Vec<&K>> {
+                    self.groups(&self.graph.components())
+                }
+
+                pub fn maximal_cliques(&self) -> Vec<Vec<&K>> {
+                    self.groups(&self.graph.maximal_cliques())
+                }
+
+                pub fn pagerank(
+                    &self,
+                    damping: f64,
+                    tolerance: f64,
+                    max_iterations: usize,
+                ) -> Vec<(&K, f64)> {
+                    self.by_key(self.graph.pagerank(damping, tolerance, max_iterations))
+                }
+
+                pub fn betweenness_centrality(&self) -> Vec<(&K, f64)> {
+                    self.by_key(self.graph.betweenness_centrality())
+                }
+
+                pub fn closeness_centrality(&self) -> Vec<(&K, f64)> {
+                    self.by_key(self.graph.closeness_centrality())
+                }
+
+                pub fn harmonic_centrality(&self) -> Vec<(&K, f64)> {
+                    self.by_key(self.graph.harmonic_centrality())
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn test_named_graph() {
+                    let mut graph: NamedGraph<&str, i32> = NamedGraph::from_edges(vec![
+                        ("A", "B", 1),
+                        ("B", "C", 2),
+                        ("A", "D", 1),
+                        ("D", "C", 1),
+                    ]);
+                    graph.add_node("E", 5);
+                    assert_eq!(graph.label(&"C"), Some(2));
+                    assert_eq!(graph.key(4), Some(&"E"));
+                    assert_eq!(graph.graph().num_nodes(), 5);
+
+                    assert_eq!(graph.bfs(&"A", &"C"), Some(vec![&"A", &"B", &"C"]));
+                    assert_eq!(graph.dfs(&"A", &"E"), None);
+                    assert_eq!(graph.bfs(&"A", &"Z"), None);
+
+                    assert_eq!(graph.remove_edge(&"A", &"B"), Some(1));
+                    assert_eq!(graph.bfs(&"A", &"C"), Some(vec![&"A", &"D", &"C"]));
+
+                    let degrees = graph.by_key(graph.graph().out_degrees());
+                    assert_eq!(degrees[0], (&"A", 1));
+                    let components = graph.graph().components();
+                    assert_eq!(graph.groups(&components)[1], vec![&"E"]);
+                }
+
+                #[test]
+                fn test_named_algorithms() {
+                    let graph: NamedGraph<&str, i32> = NamedGraph::from_edges(vec![
+                        ("A", "B", 1),
+                        ("B", "C", 5),
+                        ("A", "D", 1),
+                        ("D", "C", 1),
+                        ("E", "F", 1),
+                    ]);
+                    assert_eq!(graph.dijkstra(&"A", &"C"), Ok(Some(vec![&"A", &"D", &"C"])));
+                    assert_eq!(graph.dijkstra(&"A", &"F"), Ok(None));
+                    assert_eq!(graph.components()[1], vec![&"E", &"F"]);
+
+                    let closeness = graph.closeness_centrality();
+                    assert_eq!(closeness.len(), 6);
+                    assert_eq!(closeness[0].0, &"A");
+                    let pagerank = graph.pagerank(0.85, 1e-9, 100);
+                    assert_eq!(pagerank[5].0, &"F");
+
+                    // Algorithms without a wrapper go through the adapters
+                    let labels = graph.labels_of(&[&"A", &"C"]).unwrap();
+                    let dominators = graph.graph().dominators(labels[0]).unwrap();
+                    let idom = dominators.immediate_dominator(labels[1]).unwrap();
+                    assert_eq!(graph.key(idom), Some(&"A"));
+                    assert_eq!(graph.labels_of(&[&"A", &"Z"]), None);
+                    let colours = graph.by_key(graph.graph().dsatur_colouring());
+                    assert_eq!(colours.len(), 6);
+
+                    let (plain, keys) = graph.into_parts();
+                    let graph = NamedGraph::from_graph(plain, keys);
+                    assert_eq!(graph.label(&"D"), Some(3));
+                    assert_eq!(graph.bfs(&"A", &"C"), Some(vec![&"A", &"B", &"C"]));
+                }
+            }
+        }
+    }
+
+    // Trie!
+    pub mod trie {
+
+        use std::str::Chars;
+
+        #[derive(Debug, Default)]
+        pub struct Trie {
+            value: Option<char>,
+            children: Vec<Trie>,
+        }
+
+        impl Trie {
+            pub fn new() -> Trie {
+                Trie {
+                    value: None,
+                    children: Vec::new(),
+                }
+            }
+
+            pub fn contains(&self, t: &str) -> bool {
+                if let Some(current) = self.child_matches(t) {
+                    current.children.iter().any(|child| child.value.is_none())
+                } else {
+                    false
+                }
+            }
+
+            pub fn contains_prefix(&self, t: &str) -> bool {
+                self.child_matches(t).is_some()
+            }
+
+            fn child_matches(&self, t: &str) -> Option<&Trie> {
+                let mut current = self;
+                for c in t.chars() {
+                    let position = current
+                        .children
+                        .iter()
+                        .position(|child| child.value == Some(c));
+                    if let Some(index) = position {
+                        current = &current.children[index];
+                    } else {
+                        return None;
+                    }
+                }
+                Some(current)
+            }
+
+            pub fn add(&mut self, values: &str) {
+                let mut chars = values.chars();
+                self.add_chars(&mut chars);
+            }
+
+            fn add_chars(&mut self, values: &mut Chars) {
+                let mut current = self;
+                for c in values {
+                    let child_index = current.add_char(c);
+                    current = &mut current.children[child_index];
+                }
+
+                current.set_complete();
+            }
+
+            fn add_char(&mut self, value: char) -> usize {
+                let position = self
+                    .children
+                    .iter()
+                    .position(|child| child.value == Some(value));
+
+                if let Some(index) = position {
+                    return index;
+                }
+                self.children.push(Trie {
+                    value: Some(value),
+                    children: Vec::new(),
+                });
+                self.children.len() - 1
+            }
+
+            pub fn remove(&mut self, t: &str) -> bool {
+                let chars: Vec<char> = t.chars().collect();
+                self.remove_chars(&chars)
+            }
+
+            // Prunes any branch left with nothing under it
+            fn remove_chars(&mut self, chars: &[char]) -> bool {
+                let (first, rest) = match chars.split_first() {
+                    Some(split) => split,
+                    None => match self.children.iter().position(|child| child.value.is_none()) {
+                        Some(index) => {
+                            self.children.remove(index);
+                            return true;
+                        }
+                        None => return false,
+                    },
+                };
+
+                let index = match self
+                    .children
+                    .iter()
+                    .position(|child| child.value == Some(*first))
+                {
+                    Some(index) => index,
+                    None => return false,
+                };
+                let removed = self.children[index].remove_chars(rest);
+                if removed && self.children[index].children.is_empty() {
+                    self.children.remove(index);
+                }
+                removed
+            }
+
+            fn set_complete(&mut self) {
+                if self.children.iter().any(|child| child.value.is_none()) {
+                    return;
+                }
+
+                self.children.push(Trie {
+                    value: None,
+                    children: Vec::new(),
+                });
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn test_contains() {
+                let mut trie = Trie::new();
+
+                assert!(!trie.contains("a"));
+
+                trie.add("abc");
+                assert!(trie.contains("abc"));
+
+                assert!(!trie.contains("ab"));
+                assert!(!trie.contains("bca"));
+                assert!(!trie.contains("abcd"));
+
+                assert!(trie.contains_prefix("ab"));
+                assert!(!trie.contains_prefix("bca"));
+
+                trie.add("abcde");
+                assert!(trie.contains("abcde"));
+            }
+
+            #[test]
+            fn test_remove() {
+                let mut trie = Trie::new();
+                trie.add("ab");
+                trie.add("abcd");
+
+                assert!(!trie.remove("abc"));
+                assert!(trie.remove("abcd"));
+                assert!(!trie.contains("abcd"));
+                assert!(!trie.contains_prefix("abc"));
+                assert!(trie.contains("ab"));
+
+                assert!(trie.remove("ab"));
+                assert!(!trie.contains_prefix("a"));
+                assert!(!trie.remove("ab"));
             }
         }
     }
@@ -1,5 +1,11 @@
 pub mod data;
 
+#[allow(
+    clippy::new_without_default,
+    clippy::option_as_ref_deref,
+    clippy::mem_replace_option_with_none,
+    mismatched_lifetime_syntaxes
+)]
 pub mod linked_list {
 
     #[derive(Debug, PartialEq)]
@@ -104,6 +110,11 @@ pub mod linked_list {
     }
 
     #[cfg(test)]
+    #[allow(
+        clippy::bool_assert_comparison,
+        clippy::option_map_unit_fn,
+        clippy::useless_conversion
+    )]
     mod tests {
         use super::*;
 
@@ -190,11 +201,14 @@ pub mod linked_list {
     }
 }
 
+// Superseded by data::graph::Graph, which also has is_forest, is_arborescence and find_root
+#[allow(deprecated, clippy::new_without_default, clippy::len_zero)]
 pub mod old_graph {
     use std::collections::HashSet;
 
     type NodeIndex = usize;
 
+    #[deprecated(note = "use algorithms::data::data::graph::Graph instead")]
     #[derive(Debug)]
     pub struct Graph<T>
     where
@@ -207,6 +221,7 @@ pub mod old_graph {
 
     #[derive(Debug)]
     struct NodeData<T> {
+        #[allow(dead_code)]
         data: T,
     }
 
@@ -235,6 +250,7 @@ pub mod old_graph {
             index
         }
 
+        #[allow(clippy::result_unit_err)]
         pub fn add_edge(&mut self, source: NodeIndex, target: NodeIndex) -> Result<(), ()> {
             // should check duplicates?
 
@@ -267,70 +283,28 @@ pub mod old_graph {
             self.edges.iter().map(|e| e.len()).sum()
         }
 
+        // Ignores edge direction, and visits every edge rather than just the first
         fn is_connected(&self) -> bool {
-            let head = match self.to_head() {
-                Some(node_index) => node_index,
-                None => {
-                    return false;
-                }
-            };
-
             let mut visited = HashSet::new();
-
-            let mut target_node = 0;
-            visited.insert(target_node);
-
-            loop {
-                let current_edge = self.edges.get(target_node).unwrap();
-
-                match current_edge.get(0) {
-                    Some(edge) => {
-                        target_node = edge.target;
-                        // Cycles means the graph is not a tree
-                        if visited.contains(&target_node) {
-                            return false;
-                        }
-
-                        visited.insert(target_node);
-                    }
-                    None => {
-                        return visited.len() == self.nodes.len();
-                    }
-                };
-            }
-        }
-
-        fn to_head(&self) -> Option<NodeIndex> {
-            let mut visited = HashSet::new();
-            let edges = &self.reversed_edges;
-            // Start with the first node
-            let mut target_node = 0;
-            visited.insert(target_node);
-
-            loop {
-                // Unwrap is safe because we never add a node without
-                // also adding an empty vector of edges here.
-                let current_edges = edges.get(target_node).unwrap();
-
-                match current_edges.get(0) {
-                    Some(edge) => {
-                        target_node = edge.target;
-                        // Cycles means the graph is not a tree
-                        if visited.contains(&target_node) {
-                            return None;
-                        }
-
-                        visited.insert(target_node);
-                    }
-                    None => {
-                        return Some(target_node);
+            let mut stack = vec![0];
+            visited.insert(0);
+
+            while let Some(node) = stack.pop() {
+                let neighbours = self.edges[node]
+                    .iter()
+                    .chain(self.reversed_edges[node].iter());
+                for edge in neighbours {
+                    if visited.insert(edge.target) {
+                        stack.push(edge.target);
                     }
-                };
+                }
             }
+            visited.len() == self.nodes.len()
         }
     }
 
     #[cfg(test)]
+    #[allow(unused_must_use)]
     mod tests {
 
         use super::*;
@@ -356,9 +330,35 @@ pub mod old_graph {
             assert!(!graph.is_tree());
         }
 
+        #[test]
+        fn test_is_tree_branching() {
+            // 0 -> 1 -> 2 and 0 -> 3: following only the first edge never reaches 3
+            let mut graph = Graph::new();
+            let nodes: Vec<NodeIndex> = (0..4).map(|_| graph.add_node(1)).collect();
+            graph.add_edge(nodes[0], nodes[1]).unwrap();
+            graph.add_edge(nodes[1], nodes[2]).unwrap();
+            graph.add_edge(nodes[0], nodes[3]).unwrap();
+            assert!(graph.is_tree());
+
+            // Edges pointing into the root from node 0's point of view
+            let mut graph = Graph::new();
+            let nodes: Vec<NodeIndex> = (0..3).map(|_| graph.add_node(1)).collect();
+            graph.add_edge(nodes[1], nodes[0]).unwrap();
+            graph.add_edge(nodes[2], nodes[0]).unwrap();
+            assert!(graph.is_tree());
+
+            // Right number of edges, but a cycle and a stray node
+            let mut graph = Graph::new();
+            let nodes: Vec<NodeIndex> = (0..4).map(|_| graph.add_node(1)).collect();
+            graph.add_edge(nodes[0], nodes[1]).unwrap();
+            graph.add_edge(nodes[1], nodes[2]).unwrap();
+            graph.add_edge(nodes[2], nodes[0]).unwrap();
+            assert!(!graph.is_tree());
+        }
     }
 }
 
+#[allow(unused_imports)]
 pub mod primes {
 
     use std::iter;
@@ -463,3 +463,399 @@ pub mod primes {
         }
     }
 }
+
+// Small seeded generator so randomised algorithms are reproducible in tests.
+// Not for anything that needs real randomness.
+pub mod random {
+
+    // xorshift64*, seeded through splitmix64 so that nearby seeds diverge quickly
+    #[derive(Debug, Clone)]
+    pub struct Rng {
+        state: u64,
+    }
+
+    impl Rng {
+        pub fn new(seed: u64) -> Rng {
+            let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            // Zero is a fixed point of xorshift
+            let state = if z == 0 { 1 } else { z };
+            Rng { state }
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.state ^= self.state >> 12;
+            self.state ^= self.state << 25;
+            self.state ^= self.state >> 27;
+            self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        // Uniform in [0, 1)
+        pub fn next_f64(&mut self) -> f64 {
+            (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        // Uniform in [0, n). Panics if n is 0.
+        pub fn below(&mut self, n: usize) -> usize {
+            assert!(n > 0, "Rng::below needs a non-empty range");
+            // Multiply-shift avoids most of the modulo bias
+            ((u128::from(self.next_u64()) * n as u128) >> 64) as usize
+        }
+
+        pub fn shuffle<T>(&mut self, values: &mut [T]) {
+            for i in (1..values.len()).rev() {
+                let j = self.below(i + 1);
+                values.swap(i, j);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_seeded() {
+            let mut a = Rng::new(42);
+            let mut b = Rng::new(42);
+            let mut c = Rng::new(43);
+            let a_values: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+            let b_values: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+            let c_values: Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+            assert_eq!(a_values, b_values);
+            assert_ne!(a_values, c_values);
+        }
+
+        #[test]
+        fn test_ranges() {
+            let mut rng = Rng::new(0);
+            for _ in 0..1000 {
+                assert!(rng.below(7) < 7);
+                let f = rng.next_f64();
+                assert!((0.0..1.0).contains(&f));
+            }
+
+            let mut values: Vec<usize> = (0..20).collect();
+            rng.shuffle(&mut values);
+            values.sort_unstable();
+            assert_eq!(values, (0..20).collect::<Vec<usize>>());
+        }
+    }
+}
+
+// A small stand-in for criterion: run each implementation a few times per input
+// size and keep the timings, plus peak memory when the CountingAllocator is the
+// global allocator.
+pub mod bench {
+    use crate::data::data::graph::generators;
+    use crate::data::data::{hash_set, hash_set2};
+    use crate::primes;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::fmt;
+    use std::hint::black_box;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+    // The system allocator, keeping count of live bytes. Install it with
+    // #[global_allocator] to get memory numbers.
+    pub struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+                PEAK.fetch_max(allocated + layout.size(), Ordering::Relaxed);
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+            if !new_ptr.is_null() {
+                ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+                let allocated = ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+                PEAK.fetch_max(allocated + new_size, Ordering::Relaxed);
+            }
+            new_ptr
+        }
+    }
+
+    // Whether allocations are being counted at all
+    fn counting() -> bool {
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let probe = black_box(Box::new([0u8; 64]));
+        let counted = ALLOCATED.load(Ordering::Relaxed) != before;
+        drop(probe);
+        counted
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Group {
+        Primes,
+        HashSets,
+        Search,
+    }
+
+    pub const GROUPS: &[&str] = &["primes", "hash-sets", "search"];
+
+    impl FromStr for Group {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Group, String> {
+            match s {
+                "primes" => Ok(Group::Primes),
+                "hash-sets" => Ok(Group::HashSets),
+                "search" => Ok(Group::Search),
+                _ => Err(format!("unknown benchmark group {:?}", s)),
+            }
+        }
+    }
+
+    impl fmt::Display for Group {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let name = match self {
+                Group::Primes => "primes",
+                Group::HashSets => "hash-sets",
+                Group::Search => "search",
+            };
+            write!(f, "{}", name)
+        }
+    }
+
+    // One implementation at one input size
+    #[derive(Debug, Clone)]
+    pub struct Measurement {
+        pub group: Group,
+        pub name: &'static str,
+        pub size: usize,
+        pub samples: usize,
+        pub median: Duration,
+        pub mean: Duration,
+        pub min: Duration,
+        pub max: Duration,
+        // Most bytes live at once during a run, over what was live before it
+        pub peak_bytes: Option<usize>,
+    }
+
+    // One untimed run to warm up and measure memory, then `samples` timed runs
+    pub fn measure<R, F: FnMut() -> R>(
+        group: Group,
+        name: &'static str,
+        size: usize,
+        samples: usize,
+        mut f: F,
+    ) -> Measurement {
+        let peak_bytes = match counting() {
+            true => {
+                let baseline = ALLOCATED.load(Ordering::Relaxed);
+                PEAK.store(baseline, Ordering::Relaxed);
+                black_box(f());
+                Some(PEAK.load(Ordering::Relaxed).saturating_sub(baseline))
+            }
+            false => {
+                black_box(f());
+                None
+            }
+        };
+
+        let samples = samples.max(1);
+        let mut times: Vec<Duration> = (0..samples)
+            .map(|_| {
+                let start = Instant::now();
+                black_box(f());
+                start.elapsed()
+            })
+            .collect();
+        times.sort_unstable();
+
+        Measurement {
+            group,
+            name,
+            size,
+            samples,
+            median: times[samples / 2],
+            mean: times.iter().sum::<Duration>() / samples as u32,
+            min: times[0],
+            max: times[samples - 1],
+            peak_bytes,
+        }
+    }
+
+    // Sizes are the prime bound, the number of set elements, or the number of nodes
+    // in a random graph with four edges per node
+    pub fn run_group(group: Group, size: usize, samples: usize) -> Vec<Measurement> {
+        match group {
+            Group::Primes => vec![
+                measure(group, "trial_division", size, samples, || {
+                    primes::trial_division(black_box(size))
+                }),
+                measure(group, "sieve_of_eratosthenes_naive", size, samples, || {
+                    primes::sieve_of_eratosthenes_naive(black_box(size))
+                }),
+                measure(group, "sieve_of_eratosthenes_odds", size, samples, || {
+                    primes::sieve_of_eratosthenes_odds(black_box(size))
+                }),
+            ],
+            // The same work as the test_benchmarks_tolerable tests, plus lookups
+            Group::HashSets => vec![
+                measure(group, "hash_set::HashSet", size, samples, || {
+                    let mut set = hash_set::HashSet::new();
+                    (0..size).for_each(|i| {
+                        set.add(i);
+                    });
+                    (0..size).step_by(2).for_each(|i| {
+                        set.remove(&i);
+                    });
+                    (0..size).filter(|i| set.contains(i)).count()
+                }),
+                measure(group, "hash_set2::HashSet", size, samples, || {
+                    let mut set = hash_set2::HashSet::new();
+                    (0..size).for_each(|i| {
+                        set.add(i);
+                    });
+                    (0..size).step_by(2).for_each(|i| {
+                        set.remove(&i);
+                    });
+                    (0..size).filter(|i| set.contains(i)).count()
+                }),
+                measure(group, "std::collections::HashSet", size, samples, || {
+                    let mut set = std::collections::HashSet::new();
+                    (0..size).for_each(|i| {
+                        set.insert(i);
+                    });
+                    (0..size).step_by(2).for_each(|i| {
+                        set.remove(&i);
+                    });
+                    (0..size).filter(|i| set.contains(i)).count()
+                }),
+            ],
+            Group::Search => {
+                let graph = generators::gnm(size, 4 * size, 0);
+                let destination = size.saturating_sub(1);
+                vec![
+                    measure(group, "bfs", size, samples, || graph.bfs(0, destination)),
+                    measure(group, "dfs", size, samples, || graph.dfs(0, destination)),
+                ]
+            }
+        }
+    }
+
+    pub fn run(groups: &[Group], sizes: &[usize], samples: usize) -> Vec<Measurement> {
+        groups
+            .iter()
+            .flat_map(|group| {
+                sizes
+                    .iter()
+                    .flat_map(move |size| run_group(*group, *size, samples))
+            })
+            .collect()
+    }
+
+    fn human_bytes(bytes: usize) -> String {
+        let units = ["B", "KiB", "MiB", "GiB"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit + 1 < units.len() {
+            value /= 1024.0;
+            unit += 1;
+        }
+        match unit {
+            0 => format!("{} B", bytes),
+            _ => format!("{:.1} {}", value, units[unit]),
+        }
+    }
+
+    // Lined-up columns, one row per measurement
+    pub fn table(measurements: &[Measurement]) -> String {
+        let mut rows = vec![[
+            "group".to_string(),
+            "implementation".to_string(),
+            "size".to_string(),
+            "median".to_string(),
+            "mean".to_string(),
+            "min".to_string(),
+            "max".to_string(),
+            "peak memory".to_string(),
+        ]];
+        for m in measurements {
+            rows.push([
+                m.group.to_string(),
+                m.name.to_string(),
+                m.size.to_string(),
+                format!("{:.2?}", m.median),
+                format!("{:.2?}", m.mean),
+                format!("{:.2?}", m.min),
+                format!("{:.2?}", m.max),
+                m.peak_bytes.map_or_else(|| "-".to_string(), human_bytes),
+            ]);
+        }
+
+        let mut widths = [0; 8];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let lines: Vec<String> = rows
+            .iter()
+            .map(|row| {
+                let cells: Vec<String> = row
+                    .iter()
+                    .zip(widths.iter())
+                    .enumerate()
+                    .map(|(i, (cell, width))| match i {
+                        0 | 1 => format!("{:<width$}", cell, width = width),
+                        _ => format!("{:>width$}", cell, width = width),
+                    })
+                    .collect();
+                cells.join("  ").trim_end().to_string()
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_run() {
+            let measurements = run(&[Group::Primes, Group::Search], &[10, 100], 3);
+            let names: Vec<(Group, &str, usize)> = measurements
+                .iter()
+                .map(|m| (m.group, m.name, m.size))
+                .collect();
+            assert_eq!(names.len(), 10);
+            assert_eq!(names[0], (Group::Primes, "trial_division", 10));
+            assert_eq!(names[5], (Group::Primes, "sieve_of_eratosthenes_odds", 100));
+            assert_eq!(names[9], (Group::Search, "dfs", 100));
+            for m in measurements.iter() {
+                assert_eq!(m.samples, 3);
+                assert!(m.min <= m.median && m.median <= m.max);
+                // Tests run on the system allocator
+                assert_eq!(m.peak_bytes, None);
+            }
+
+            let table = table(&measurements[..1]);
+            let lines: Vec<&str> = table.lines().collect();
+            assert_eq!(lines.len(), 2);
+            assert!(lines[0].starts_with("group   implementation  size"));
+            assert!(lines[1].starts_with("primes  trial_division    10"));
+            assert!(lines[1].ends_with('-'));
+            assert_eq!(human_bytes(512), "512 B");
+            assert_eq!(human_bytes(3 * 1024 * 1024 / 2), "1.5 MiB");
+        }
+    }
+}
@@ -1,24 +1,75 @@
 extern crate algorithms;
-use algorithms::data::data::graph::Graph;
+use algorithms::bench;
+use algorithms::data::data::graph::dot::{DotOptions, SearchFrame};
+use algorithms::data::data::graph::io::{self as graph_io, GraphFile, NamedGraphFile};
+use algorithms::data::data::graph::named::NamedGraph;
+use algorithms::data::data::graph::{formats, generators, NodeLabel, Search, TraceEvent};
 
 #[macro_use]
 extern crate clap;
 
 use clap::{App, Arg, SubCommand};
-use itertools::Itertools;
+use output::{Format, SearchReport};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::time::{Duration, Instant};
 
-fn main() -> Result<(), std::io::Error> {
+mod output;
+mod repl;
+
+// Counts allocations so `bench` can report memory. Behind a feature so the other
+// commands don't pay for it.
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: bench::CountingAllocator = bench::CountingAllocator;
+
+const GRAPH_FORMATS: &[&str] = &[
+    "native",
+    "dimacs",
+    "edge-list",
+    "weighted-edge-list",
+    "matrix-market",
+    "graphml",
+];
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), std::io::Error> {
     let matches = App::new("algorithms")
         .about("Practice with algorithms and data structures")
         .version(crate_version!())
+        .arg(
+            Arg::with_name("format")
+                .help("How to print results from bfs, dfs, stats, query, repl and bench. generate and convert write graph files and ignore it")
+                .long("format")
+                .global(true)
+                .takes_value(true)
+                .possible_values(output::FORMATS)
+                .default_value("text"),
+        )
         .subcommand(
             SubCommand::with_name("bfs")
                 .about("Breadth First Search on a graph.")
                 .arg(
                     Arg::with_name("input")
-                        .help("The file with the input graph")
+                        .help("The graph file (native or named), or - for stdin")
                         .index(1)
                         .required(true),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("Print the graph in Graphviz DOT format with the path highlighted")
+                        .long("dot"),
+                )
+                .arg(
+                    Arg::with_name("trace")
+                        .help("Print every step of the search to stderr, or as DOT frames with --dot")
+                        .long("trace"),
                 ),
         )
         .subcommand(
@@ -26,61 +77,772 @@ fn main() -> Result<(), std::io::Error> {
                 .about("Depth First Search on a graph.")
                 .arg(
                     Arg::with_name("input")
-                        .help("The file with the input graph")
+                        .help("The graph file (native or named), or - for stdin")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .help("Print the graph in Graphviz DOT format with the path highlighted")
+                        .long("dot"),
+                )
+                .arg(
+                    Arg::with_name("trace")
+                        .help("Print every step of the search to stderr, or as DOT frames with --dot")
+                        .long("trace"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Summary statistics for a graph.")
+                .arg(
+                    Arg::with_name("input")
+                        .help("The graph file (native or named), or - for stdin")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("query")
+                .about("Run many searches against one graph, one `algorithm source destination` per line.")
+                .arg(
+                    Arg::with_name("input")
+                        .help("The graph file (native or named), or - for stdin")
                         .index(1)
                         .required(true),
+                )
+                .arg(
+                    Arg::with_name("queries")
+                        .help("The file with the queries, or - for stdin")
+                        .index(2)
+                        .default_value("-"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("repl")
+                .about("Explore graphs, the stack, the hash sets and the trie interactively.")
+                .arg(
+                    Arg::with_name("input")
+                        .help("A graph file to load first")
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Time the primes, hash set and graph search implementations against each other. Peak memory needs --features count-allocations.")
+                .arg(
+                    Arg::with_name("groups")
+                        .help("Which implementations to compare [default: all]")
+                        .index(1)
+                        .multiple(true)
+                        .possible_values(bench::GROUPS),
+                )
+                .arg(
+                    Arg::with_name("sizes")
+                        .help("Comma separated input sizes: the prime bound, set elements or graph nodes")
+                        .long("sizes")
+                        .takes_value(true)
+                        .default_value("1000,10000,100000"),
+                )
+                .arg(
+                    Arg::with_name("samples")
+                        .help("Timed runs per implementation and size")
+                        .long("samples")
+                        .takes_value(true)
+                        .default_value("10"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Generate a random or regular graph file.")
+                .arg(
+                    Arg::with_name("model")
+                        .help("The kind of graph to generate")
+                        .index(1)
+                        .required(true)
+                        .possible_values(&[
+                            "gnp",
+                            "gnm",
+                            "barabasi-albert",
+                            "watts-strogatz",
+                            "dag",
+                            "tree",
+                            "grid",
+                            "complete",
+                            "bipartite",
+                        ]),
+                )
+                .arg(
+                    Arg::with_name("nodes")
+                        .help("Number of nodes (left side for bipartite)")
+                        .long("nodes")
+                        .short("n")
+                        .takes_value(true)
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::with_name("edges")
+                        .help("Number of edges for gnm")
+                        .long("edges")
+                        .short("m")
+                        .takes_value(true)
+                        .default_value("20"),
+                )
+                .arg(
+                    Arg::with_name("probability")
+                        .help("Edge probability for gnp and dag, obstacle probability for grid")
+                        .long("probability")
+                        .short("p")
+                        .takes_value(true)
+                        .default_value("0.2"),
+                )
+                .arg(
+                    Arg::with_name("degree")
+                        .help("Edges per new node for barabasi-albert, ring degree for watts-strogatz")
+                        .long("degree")
+                        .short("k")
+                        .takes_value(true)
+                        .default_value("2"),
+                )
+                .arg(
+                    Arg::with_name("rewire")
+                        .help("Rewiring probability for watts-strogatz")
+                        .long("rewire")
+                        .takes_value(true)
+                        .default_value("0.1"),
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .help("Grid width")
+                        .long("width")
+                        .takes_value(true)
+                        .default_value("5"),
+                )
+                .arg(
+                    Arg::with_name("height")
+                        .help("Grid height")
+                        .long("height")
+                        .takes_value(true)
+                        .default_value("5"),
+                )
+                .arg(
+                    Arg::with_name("right")
+                        .help("Right side size for bipartite")
+                        .long("right")
+                        .takes_value(true)
+                        .default_value("5"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .help("Random seed")
+                        .long("seed")
+                        .short("s")
+                        .takes_value(true)
+                        .default_value("0"),
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .help("Source node written to the file [default: 0]")
+                        .long("source")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("destination")
+                        .help("Destination node written to the file [default: last node]")
+                        .long("destination")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("Where to write the graph [default: stdout]")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("Convert a graph file between formats. Node payloads are dropped.")
+                .arg(
+                    Arg::with_name("input")
+                        .help("The graph file (native or named), or - for stdin")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("Where to write the graph [default: stdout]")
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .help("Format of the input graph")
+                        .long("from")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(GRAPH_FORMATS),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .help("Format of the output graph")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(GRAPH_FORMATS),
                 ),
         )
         .get_matches();
 
-    if let Some(matches) = matches.subcommand_matches("bfs") {
+    for algorithm in ["bfs", "dfs"].iter() {
+        if let Some(matches) = matches.subcommand_matches(algorithm) {
+            let format = value_t!(matches, "format", Format).unwrap_or_else(|e| e.exit());
+            let input = matches.value_of("input").unwrap();
+            let loaded = read_graph_file(input)?;
+            let report = match matches.is_present("trace") {
+                true => print_trace(&loaded, algorithm, matches.is_present("dot")),
+                false => search(&loaded, algorithm, loaded.source, loaded.destination)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            };
+            if matches.is_present("dot") {
+                print_dot(&loaded, report.labels.as_deref());
+                continue;
+            }
+            if format == Format::Csv {
+                println!("{}", output::SEARCH_CSV_HEADER);
+            }
+            println!("{}", report.render(format));
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("stats") {
+        let format = value_t!(matches, "format", Format).unwrap_or_else(|e| e.exit());
+        let input = matches.value_of("input").unwrap();
+        let loaded = read_graph_file(input)?;
+        println!(
+            "{}",
+            output::render_stats(&loaded.graph.graph().stats(), format)
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("query") {
+        let format = value_t!(matches, "format", Format).unwrap_or_else(|e| e.exit());
         let input = matches.value_of("input").unwrap();
-        let (graph, source, destination) = parse_graph_file(input)?;
-        println!("{:?}", graph.bfs(source, destination));
+        let queries = matches.value_of("queries").unwrap();
+        if input == "-" && queries == "-" {
+            clap::Error::with_description(
+                "the graph and the queries can't both come from stdin",
+                clap::ErrorKind::ArgumentConflict,
+            )
+            .exit();
+        }
+        let loaded = read_graph_file(input)?;
+        let stdout = std::io::stdout();
+        let failed = run_queries(
+            &loaded,
+            BufReader::new(open_input(queries)?),
+            std::io::BufWriter::new(stdout.lock()),
+            format,
+        )?;
+        if failed > 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} of the queries failed", failed),
+            ));
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("repl") {
+        let format = value_t!(matches, "format", Format).unwrap_or_else(|e| e.exit());
+        let mut repl = repl::Repl::new(format);
+        if let Some(input) = matches.value_of("input") {
+            println!("{}", repl.execute(&format!("load {}", input)));
+        }
+        let stdin = std::io::stdin();
+        repl::run(&mut repl, stdin.lock(), std::io::stdout())?;
     }
 
-    if let Some(matches) = matches.subcommand_matches("dfs") {
+    if let Some(matches) = matches.subcommand_matches("bench") {
+        let format = value_t!(matches, "format", Format).unwrap_or_else(|e| e.exit());
+        let samples = value_t!(matches, "samples", usize).unwrap_or_else(|e| e.exit());
+        let groups = match matches.values_of("groups") {
+            Some(groups) => groups.map(|group| group.parse().unwrap()).collect(),
+            None => vec![
+                bench::Group::Primes,
+                bench::Group::HashSets,
+                bench::Group::Search,
+            ],
+        };
+        let sizes: Vec<usize> = matches
+            .value_of("sizes")
+            .unwrap()
+            .split(',')
+            .map(|size| size.trim().parse())
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|_| {
+                clap::Error::value_validation_auto("--sizes must be whole numbers".to_string())
+                    .exit()
+            });
+        let measurements = bench::run(&groups, &sizes, samples);
+        println!("{}", output::render_measurements(&measurements, format));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        let nodes = value_t!(matches, "nodes", usize).unwrap_or_else(|e| e.exit());
+        let seed = value_t!(matches, "seed", u64).unwrap_or_else(|e| e.exit());
+        let probability = value_t!(matches, "probability", f64).unwrap_or_else(|e| e.exit());
+        let degree = value_t!(matches, "degree", usize).unwrap_or_else(|e| e.exit());
+
+        let model = matches.value_of("model").unwrap();
+        let graph = match model {
+            "gnp" => generators::gnp(nodes, probability, seed),
+            "gnm" => {
+                let edges = value_t!(matches, "edges", usize).unwrap_or_else(|e| e.exit());
+                generators::gnm(nodes, edges, seed)
+            }
+            "barabasi-albert" => generators::barabasi_albert(nodes, degree, seed),
+            "watts-strogatz" => {
+                let rewire = value_t!(matches, "rewire", f64).unwrap_or_else(|e| e.exit());
+                generators::watts_strogatz(nodes, degree, rewire, seed)
+            }
+            "dag" => generators::random_dag(nodes, probability, seed),
+            "tree" => generators::random_tree(nodes, seed),
+            "grid" => {
+                let width = value_t!(matches, "width", usize).unwrap_or_else(|e| e.exit());
+                let height = value_t!(matches, "height", usize).unwrap_or_else(|e| e.exit());
+                generators::grid(width, height, probability, seed)
+            }
+            "complete" => generators::complete(nodes),
+            "bipartite" => {
+                let right = value_t!(matches, "right", usize).unwrap_or_else(|e| e.exit());
+                generators::complete_bipartite(nodes, right)
+            }
+            _ => unreachable!(),
+        };
+
+        let source = match matches.is_present("source") {
+            true => value_t!(matches, "source", usize).unwrap_or_else(|e| e.exit()),
+            false => 0,
+        };
+        let destination = match matches.is_present("destination") {
+            true => value_t!(matches, "destination", usize).unwrap_or_else(|e| e.exit()),
+            false => graph.num_nodes().saturating_sub(1),
+        };
+        for (name, node) in [("source", source), ("destination", destination)].iter() {
+            if *node >= graph.num_nodes() {
+                clap::Error::with_description(
+                    &format!(
+                        "the {} node {} is out of range for {} nodes",
+                        name,
+                        node,
+                        graph.num_nodes()
+                    ),
+                    clap::ErrorKind::ValueValidation,
+                )
+                .exit();
+            }
+        }
+        // DAGs and trees point away from their roots. The other models are undirected,
+        // so their files list each edge once.
+        let file = GraphFile {
+            graph,
+            source,
+            destination,
+            directed: matches!(model, "dag" | "tree"),
+        };
+        let mut contents = Vec::new();
+        graph_io::write_graph(&file, &mut contents)?;
+        match matches.value_of("output") {
+            Some(output) => std::fs::write(output, contents)?,
+            None => std::io::stdout().write_all(&contents)?,
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("convert") {
         let input = matches.value_of("input").unwrap();
-        let (graph, source, destination) = parse_graph_file(input)?;
-        println!("{:?}", graph.dfs(source, destination));
+        // Native files keep their source and destination when written back as native
+        let mut endpoints = None;
+        let graph = match matches.value_of("from").unwrap() {
+            "native" => {
+                let loaded = read_graph_file(input)?;
+                endpoints = Some((loaded.source, loaded.destination));
+                loaded.graph.graph().map_nodes(|_| 0)
+            }
+            "dimacs" => formats::read_dimacs(BufReader::new(open_input(input)?))?,
+            "edge-list" | "weighted-edge-list" => {
+                formats::read_edge_list(BufReader::new(open_input(input)?))?
+            }
+            "matrix-market" => formats::read_matrix_market(BufReader::new(open_input(input)?))?,
+            "graphml" => formats::read_graphml(open_input(input)?)?,
+            _ => unreachable!(),
+        };
+
+        let mut contents = Vec::new();
+        match matches.value_of("to").unwrap() {
+            "native" => {
+                let (source, destination) =
+                    endpoints.unwrap_or((0, graph.num_nodes().saturating_sub(1)));
+                let file = GraphFile {
+                    graph,
+                    source,
+                    destination,
+                    directed: true,
+                };
+                graph_io::write_graph(&file, &mut contents)?;
+            }
+            "dimacs" => formats::write_dimacs(&graph, &mut contents)?,
+            "edge-list" => formats::write_edge_list(&graph, &mut contents, false)?,
+            "weighted-edge-list" => formats::write_edge_list(&graph, &mut contents, true)?,
+            "matrix-market" => formats::write_matrix_market(&graph, &mut contents)?,
+            "graphml" => formats::write_graphml(&graph, &mut contents)?,
+            _ => unreachable!(),
+        }
+        match matches.value_of("output") {
+            Some(output) => std::fs::write(output, contents)?,
+            None => std::io::stdout().write_all(&contents)?,
+        }
     }
     Ok(())
 }
 
-fn parse_graph_file(input: &str) -> Result<(Graph<i32>, usize, usize), std::io::Error> {
-    let data = std::fs::read_to_string(input)?;
-    let mut lines = data.lines();
-    let num_nodes = lines
-        .next()
-        .expect("Bad graph file")
-        .parse::<usize>()
-        .expect("Could not parse number of nodes");
-
-    let nodes = vec![0; num_nodes];
-
-    let edges_str = lines.next().expect("Bad graph file");
-
-    let edges: Vec<(usize, usize)> = edges_str
-        .split(|c| c == ']' || c == ',' || c == '[')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<usize>())
-        .map(|s| s.unwrap())
-        .tuples()
-        .collect();
-
-    let source = lines
-        .next()
-        .expect("Bad graph file")
-        .parse::<usize>()
-        .expect("Could not parse source id");
-
-    let destination = lines
-        .next()
-        .expect("Bad graph file")
-        .parse::<usize>()
-        .expect("Could not parse source id");
-
-    let graph = Graph::new_unweighted(nodes, edges);
-    Ok((graph, source, destination))
+// A graph file in either the native or the named format. Native nodes are keyed by
+// their labels, so "3" is node 3. Named graphs keep their names as node payloads too,
+// so they show up in DOT output.
+struct LoadedGraph {
+    graph: NamedGraph<String, String>,
+    source: NodeLabel,
+    destination: NodeLabel,
+    named: bool,
+}
+
+impl LoadedGraph {
+    fn node_name(&self, node: NodeLabel) -> String {
+        self.graph.key(node).unwrap().clone()
+    }
+
+    fn label(&self, name: &str) -> Result<NodeLabel, String> {
+        self.graph
+            .label(name)
+            .ok_or_else(|| format!("there is no node {:?}", name))
+    }
+
+    // Named graphs grow to fit new names
+    fn label_or_add(&mut self, name: &str) -> Result<NodeLabel, String> {
+        match self.label(name) {
+            Err(_) if self.named => Ok(self.graph.add_node(name.to_string(), name.to_string())),
+            label => label,
+        }
+    }
+}
+
+// Runs and times one bfs, dfs or dijkstra. Only dijkstra can fail, on a graph
+// with negative weights.
+fn search<'a>(
+    loaded: &LoadedGraph,
+    algorithm: &'a str,
+    source: NodeLabel,
+    destination: NodeLabel,
+) -> Result<SearchReport<'a>, String> {
+    let start = Instant::now();
+    let graph = loaded.graph.graph();
+    let search = match algorithm {
+        "bfs" => graph.bfs_search(source, destination),
+        "dfs" => graph.dfs_search(source, destination),
+        _ => graph
+            .dijkstra_search(source, destination)
+            .map_err(|e| e.to_string())?,
+    };
+    Ok(report(
+        loaded,
+        algorithm,
+        source,
+        destination,
+        search,
+        start.elapsed(),
+    ))
+}
+
+// A finished search with its nodes named
+fn report<'a>(
+    loaded: &LoadedGraph,
+    algorithm: &'a str,
+    source: NodeLabel,
+    destination: NodeLabel,
+    search: Search,
+    elapsed: Duration,
+) -> SearchReport<'a> {
+    SearchReport {
+        algorithm,
+        source: loaded.node_name(source),
+        destination: loaded.node_name(destination),
+        path: search
+            .path
+            .as_ref()
+            .map(|path| path.iter().map(|node| loaded.node_name(*node)).collect()),
+        cost: search
+            .path
+            .as_ref()
+            .and_then(|path| loaded.graph.graph().path_cost(path)),
+        expanded: search.expanded,
+        elapsed,
+        named: loaded.named,
+        labels: search.path,
+    }
+}
+
+// Runs one `algorithm source destination` query line
+fn query<'a>(loaded: &LoadedGraph, fields: &[&'a str]) -> Result<SearchReport<'a>, String> {
+    let (algorithm, source, destination) = match fields {
+        [algorithm, source, destination] => (*algorithm, *source, *destination),
+        _ => return Err("expected `algorithm source destination`".to_string()),
+    };
+    if !matches!(algorithm, "bfs" | "dfs" | "dijkstra") {
+        return Err(format!("unknown algorithm {:?}", algorithm));
+    }
+    let (source, destination) = (loaded.label(source)?, loaded.label(destination)?);
+    search(loaded, algorithm, source, destination)
+}
+
+// One result per query line, with an error row in place of lines that can't run.
+// Returns how many lines failed.
+fn run_queries<R: BufRead, W: Write>(
+    loaded: &LoadedGraph,
+    queries: R,
+    mut out: W,
+    format: Format,
+) -> Result<usize, std::io::Error> {
+    if format == Format::Csv {
+        writeln!(out, "{}", output::QUERY_CSV_HEADER)?;
+    }
+
+    let mut failed = 0;
+    for (index, line) in queries.lines().enumerate() {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.first() {
+            None => continue,
+            Some(first) if first.starts_with('#') => continue,
+            Some(_) => {}
+        }
+        match query(loaded, &fields) {
+            Ok(report) => writeln!(out, "{}", report.render_query(format))?,
+            Err(e) => {
+                failed += 1;
+                let row = output::render_query_error(index + 1, &fields, &e, format);
+                writeln!(out, "{}", row)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(failed)
+}
+
+fn print_dot(loaded: &LoadedGraph, path: Option<&[NodeLabel]>) {
+    let options = DotOptions {
+        node_labels: loaded.named,
+        highlight: path,
+        ..DotOptions::default()
+    };
+    print!("{}", loaded.graph.graph().to_dot(&options));
+}
+
+// Runs a bfs or dfs, printing each step to stderr, or a DOT frame after each step to
+// stdout. The report comes from the same run.
+fn print_trace<'a>(loaded: &LoadedGraph, algorithm: &'a str, dot: bool) -> SearchReport<'a> {
+    let options = DotOptions {
+        node_labels: loaded.named,
+        ..DotOptions::default()
+    };
+    let graph = loaded.graph.graph();
+    let mut frame = SearchFrame::new();
+    let mut step = |event: TraceEvent| {
+        let line = event.map(|node| loaded.node_name(node));
+        if dot {
+            frame.apply(event);
+            println!("// {}", line);
+            print!("{}", graph.to_dot_frame(&frame, &options));
+        } else {
+            eprintln!("{}", line);
+        }
+    };
+    let start = Instant::now();
+    let search = match algorithm {
+        "bfs" => graph.bfs_traced(loaded.source, loaded.destination, &mut step),
+        _ => graph.dfs_traced(loaded.source, loaded.destination, &mut step),
+    };
+    let elapsed = start.elapsed();
+    report(
+        loaded,
+        algorithm,
+        loaded.source,
+        loaded.destination,
+        search,
+        elapsed,
+    )
+}
+
+// A file, or stdin for -
+fn open_input(input: &str) -> Result<Box<dyn Read>, std::io::Error> {
+    match input {
+        "-" => Ok(Box::new(std::io::stdin())),
+        path => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+// Native files start with a node count or a directed/undirected line. Named files
+// can too, since a lone word declares a node, so this only picks which error to report.
+fn is_native(text: &str) -> bool {
+    let first = text
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'));
+    match first {
+        Some(line) => line.parse::<usize>().is_ok() || line == "directed" || line == "undirected",
+        None => true,
+    }
+}
+
+// Malformed files are reported with their line and column. Files that don't parse as
+// native graphs are tried as named ones.
+fn load_graph(input: &str) -> Result<LoadedGraph, String> {
+    let mut text = String::new();
+    open_input(input)
+        .and_then(|mut reader| reader.read_to_string(&mut text))
+        .map_err(|e| format!("{}: {}", input, e))?;
+
+    let native = graph_io::read_graph(text.as_bytes()).map(|file: GraphFile<String>| {
+        let keys = (0..file.graph.num_nodes()).map(|n| n.to_string()).collect();
+        LoadedGraph {
+            graph: NamedGraph::from_graph(file.graph, keys),
+            source: file.source,
+            destination: file.destination,
+            named: false,
+        }
+    });
+    let loaded = native.or_else(|native_error| {
+        graph_io::read_named_graph(text.as_bytes())
+            .map(|file: NamedGraphFile<String>| {
+                let mut graph = file.graph;
+                for node in 0..graph.graph().num_nodes() {
+                    let name = graph.key(node).unwrap().clone();
+                    graph.add_node(name.clone(), name);
+                }
+                LoadedGraph {
+                    graph,
+                    source: file.source,
+                    destination: file.destination,
+                    named: true,
+                }
+            })
+            .map_err(|named_error| match is_native(&text) {
+                true => native_error,
+                false => named_error,
+            })
+    });
+    loaded.map_err(|e| format!("{}: {}", input, e))
+}
+
+// load_graph for callers that deal in io errors
+fn read_graph_file(input: &str) -> Result<LoadedGraph, std::io::Error> {
+    load_graph(input).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_graph_file() {
+        let loaded = read_graph_file("tests/graph_search_1.txt").unwrap();
+        assert_eq!((loaded.source, loaded.destination), (0, 4));
+
+        let path = std::env::temp_dir().join("algorithms_test_read_graph_file.txt");
+        std::fs::write(&path, "3\n[[0, 1]\n0\n2\n").unwrap();
+        let error = read_graph_file(path.to_str().unwrap()).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error
+            .to_string()
+            .ends_with("line 2, column 8: expected ']'"));
+    }
+
+    #[test]
+    fn test_run_queries() {
+        let loaded = read_graph_file("tests/graph_search_1.txt").unwrap();
+        let queries = "# comment\nbfs 0 4\n\ndijkstra 2 0\nbfs 0\nastar 0 4\ndfs 0 10\n";
+        let mut out = Vec::new();
+        let failed = run_queries(&loaded, queries.as_bytes(), &mut out, Format::Text).unwrap();
+        assert_eq!(failed, 3);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "bfs 0 4: Some([0, 1, 2, 4])\n\
+             dijkstra 2 0: None\n\
+             line 5: error: expected `algorithm source destination`\n\
+             line 6: error: unknown algorithm \"astar\"\n\
+             line 7: error: there is no node \"10\"\n"
+        );
+
+        let mut out = Vec::new();
+        let failed = run_queries(
+            &loaded,
+            "dfs 0 4\nbfs 0 10\n".as_bytes(),
+            &mut out,
+            Format::Csv,
+        );
+        assert_eq!(failed.unwrap(), 1);
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], output::QUERY_CSV_HEADER);
+        assert!(lines[1].starts_with("dfs,0,4,true,0 1 2 3 4,4,"));
+        assert!(lines[1].ends_with(','));
+        assert_eq!(
+            lines[2],
+            "bfs,0,10,false,,,,,,\"line 2: there is no node \"\"10\"\"\""
+        );
+    }
+
+    #[test]
+    fn test_run_queries_negative_weight() {
+        // dijkstra refuses the graph, even though 0 -> 1 never touches the bad edge
+        let path = std::env::temp_dir().join("algorithms_test_run_queries_negative_weight.txt");
+        std::fs::write(&path, "3\n[[0, 1, 2], [1, 2, -5]]\n0\n2\n").unwrap();
+        let loaded = read_graph_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut out = Vec::new();
+        let queries = "dijkstra 0 1\nbfs 0 2\n";
+        let failed = run_queries(&loaded, queries.as_bytes(), &mut out, Format::Text).unwrap();
+        assert_eq!(failed, 1);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "line 1: error: edge 1 -> 2 has negative weight -5, which dijkstra can't handle\n\
+             bfs 0 2: Some([0, 1, 2])\n"
+        );
+    }
+
+    #[test]
+    fn test_load_named_graph() {
+        // A lone number is a node in a named file, not a node count
+        let path = std::env::temp_dir().join("algorithms_test_load_named_graph.txt");
+        std::fs::write(&path, "5\nA -> B\nsource A\ndestination B\n").unwrap();
+        let mut loaded = load_graph(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.named);
+        assert_eq!(loaded.label("5"), Ok(0));
+        assert_eq!((loaded.source, loaded.destination), (1, 2));
+        let options = DotOptions {
+            node_labels: true,
+            ..DotOptions::default()
+        };
+        assert!(loaded
+            .graph
+            .graph()
+            .to_dot(&options)
+            .contains("[label=\"1: A\"]"));
+        assert_eq!(loaded.label_or_add("C"), Ok(3));
+        assert_eq!(loaded.node_name(3), "C");
+
+        let mut loaded = load_graph("tests/graph_search_1.txt").unwrap();
+        assert!(!loaded.named);
+        assert_eq!(loaded.label("4"), Ok(4));
+        assert!(loaded.label_or_add("10").is_err());
+    }
 }
@@ -0,0 +1,385 @@
+// Rendering results for the command line as text, JSON or CSV
+use algorithms::bench::{self, Measurement};
+use algorithms::data::data::graph::stats::GraphStats;
+use algorithms::data::data::graph::NodeLabel;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const FORMATS: &[&str] = &["text", "json", "csv"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {:?}", s)),
+        }
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// Quoted only when it has to be
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_option<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "null".to_string(),
+    }
+}
+
+fn csv_option<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
+// One bfs or dfs run. Nodes are already rendered, as names or numbers.
+pub struct SearchReport<'a> {
+    pub algorithm: &'a str,
+    pub source: String,
+    pub destination: String,
+    pub path: Option<Vec<String>>,
+    pub cost: Option<i64>,
+    pub expanded: usize,
+    pub elapsed: Duration,
+    // Named nodes are strings in JSON and quoted in text
+    pub named: bool,
+    // The path as node labels, for highlighting
+    pub labels: Option<Vec<NodeLabel>>,
+}
+
+pub const SEARCH_CSV_HEADER: &str =
+    "algorithm,source,destination,found,path,length,cost,expanded,time_us";
+
+// Query rows have room for the reason a line couldn't run
+pub const QUERY_CSV_HEADER: &str =
+    "algorithm,source,destination,found,path,length,cost,expanded,time_us,error";
+
+impl<'a> SearchReport<'a> {
+    fn node_json(&self, node: &str) -> String {
+        match self.named {
+            true => json_string(node),
+            false => node.to_string(),
+        }
+    }
+
+    fn node_text(&self, node: &str) -> String {
+        match self.named {
+            true => format!("{:?}", node),
+            false => node.to_string(),
+        }
+    }
+
+    fn length(&self) -> Option<usize> {
+        self.path.as_ref().map(|path| path.len().saturating_sub(1))
+    }
+
+    // Text keeps the original `Some([0, 1, 2])` output
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => match &self.path {
+                Some(path) => {
+                    let nodes: Vec<String> = path.iter().map(|node| self.node_text(node)).collect();
+                    format!("Some([{}])", nodes.join(", "))
+                }
+                None => "None".to_string(),
+            },
+            Format::Json => {
+                let path = self.path.as_ref().map(|path| {
+                    let nodes: Vec<String> = path.iter().map(|node| self.node_json(node)).collect();
+                    format!("[{}]", nodes.join(","))
+                });
+                format!(
+                    "{{\"algorithm\":{},\"source\":{},\"destination\":{},\"found\":{},\"path\":{},\"length\":{},\"cost\":{},\"expanded\":{},\"time_us\":{}}}",
+                    json_string(self.algorithm),
+                    self.node_json(&self.source),
+                    self.node_json(&self.destination),
+                    self.path.is_some(),
+                    json_option(path),
+                    json_option(self.length()),
+                    json_option(self.cost),
+                    self.expanded,
+                    self.elapsed.as_micros()
+                )
+            }
+            Format::Csv => {
+                let path = self.path.as_ref().map(|path| path.join(" "));
+                let fields = [
+                    self.algorithm.to_string(),
+                    self.source.clone(),
+                    self.destination.clone(),
+                    self.path.is_some().to_string(),
+                    path.unwrap_or_default(),
+                    csv_option(self.length()),
+                    csv_option(self.cost),
+                    self.expanded.to_string(),
+                    self.elapsed.as_micros().to_string(),
+                ];
+                let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                fields.join(",")
+            }
+        }
+    }
+
+    // Text says which search each line answers. CSV rows end with an empty error.
+    pub fn render_query(&self, format: Format) -> String {
+        match format {
+            Format::Text => format!(
+                "{} {} {}: {}",
+                self.algorithm,
+                self.node_text(&self.source),
+                self.node_text(&self.destination),
+                self.render(format)
+            ),
+            Format::Json => self.render(format),
+            Format::Csv => format!("{},", self.render(format)),
+        }
+    }
+}
+
+// A query line that couldn't run, in place of its result. CSV keeps whatever
+// algorithm, source and destination the line had.
+pub fn render_query_error(
+    line_number: usize,
+    fields: &[&str],
+    error: &str,
+    format: Format,
+) -> String {
+    match format {
+        Format::Text => format!("line {}: error: {}", line_number, error),
+        Format::Json => format!(
+            "{{\"line\":{},\"error\":{}}}",
+            line_number,
+            json_string(error)
+        ),
+        Format::Csv => {
+            let field = |index: usize| csv_field(fields.get(index).unwrap_or(&""));
+            format!(
+                "{},{},{},false,,,,,,{}",
+                field(0),
+                field(1),
+                field(2),
+                csv_field(&format!("line {}: {}", line_number, error))
+            )
+        }
+    }
+}
+
+fn json_histogram(histogram: &BTreeMap<usize, usize>) -> String {
+    let entries: Vec<String> = histogram
+        .iter()
+        .map(|(degree, count)| format!("\"{}\":{}", degree, count))
+        .collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+// Text is the existing summary. JSON and CSV carry the scalar numbers, and JSON the
+// degree histograms and component sizes as well.
+pub fn render_stats(stats: &GraphStats, format: Format) -> String {
+    let average_clustering = match stats.num_nodes {
+        0 => 0.0,
+        n => stats.local_clustering.iter().sum::<f64>() / n as f64,
+    };
+    let scalars = [
+        ("nodes", stats.num_nodes.to_string()),
+        ("edges", stats.num_edges.to_string()),
+        ("self_loops", stats.self_loops.to_string()),
+        ("components", stats.component_sizes.len().to_string()),
+        ("diameter", json_option(stats.diameter)),
+        ("radius", json_option(stats.radius)),
+        ("triangles", stats.triangles.to_string()),
+        ("average_clustering", average_clustering.to_string()),
+        ("global_clustering", stats.global_clustering.to_string()),
+    ];
+    match format {
+        Format::Text => stats.to_string().trim_end().to_string(),
+        Format::Json => {
+            let mut fields: Vec<String> = scalars
+                .iter()
+                .map(|(name, value)| format!("\"{}\":{}", name, value))
+                .collect();
+            let sizes: Vec<String> = stats
+                .component_sizes
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+            fields.push(format!("\"component_sizes\":[{}]", sizes.join(",")));
+            fields.push(format!(
+                "\"in_degrees\":{}",
+                json_histogram(&stats.in_degrees)
+            ));
+            fields.push(format!(
+                "\"out_degrees\":{}",
+                json_histogram(&stats.out_degrees)
+            ));
+            format!("{{{}}}", fields.join(","))
+        }
+        Format::Csv => {
+            let mut lines = vec!["metric,value".to_string()];
+            for (name, value) in scalars.iter() {
+                let value = match value.as_str() {
+                    "null" => "",
+                    value => value,
+                };
+                lines.push(format!("{},{}", name, value));
+            }
+            lines.join("\n")
+        }
+    }
+}
+
+pub const BENCH_CSV_HEADER: &str =
+    "group,implementation,size,samples,median_ns,mean_ns,min_ns,max_ns,peak_bytes";
+
+// Text is a table. JSON is an array with durations in nanoseconds.
+pub fn render_measurements(measurements: &[Measurement], format: Format) -> String {
+    let fields = |m: &Measurement| {
+        [
+            m.size.to_string(),
+            m.samples.to_string(),
+            m.median.as_nanos().to_string(),
+            m.mean.as_nanos().to_string(),
+            m.min.as_nanos().to_string(),
+            m.max.as_nanos().to_string(),
+        ]
+    };
+    match format {
+        Format::Text => bench::table(measurements),
+        Format::Json => {
+            let names = [
+                "size",
+                "samples",
+                "median_ns",
+                "mean_ns",
+                "min_ns",
+                "max_ns",
+            ];
+            let objects: Vec<String> = measurements
+                .iter()
+                .map(|m| {
+                    let mut object = vec![
+                        format!("\"group\":{}", json_string(&m.group.to_string())),
+                        format!("\"implementation\":{}", json_string(m.name)),
+                    ];
+                    for (name, value) in names.iter().zip(fields(m).iter()) {
+                        object.push(format!("\"{}\":{}", name, value));
+                    }
+                    object.push(format!("\"peak_bytes\":{}", json_option(m.peak_bytes)));
+                    format!("{{{}}}", object.join(","))
+                })
+                .collect();
+            format!("[{}]", objects.join(","))
+        }
+        Format::Csv => {
+            let mut lines = vec![BENCH_CSV_HEADER.to_string()];
+            for m in measurements {
+                let mut row = vec![csv_field(&m.group.to_string()), csv_field(m.name)];
+                row.extend(fields(m).iter().cloned());
+                row.push(csv_option(m.peak_bytes));
+                lines.push(row.join(","));
+            }
+            lines.join("\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(path: Option<Vec<&str>>, named: bool) -> SearchReport<'static> {
+        SearchReport {
+            algorithm: "bfs",
+            source: "a,\"b\"".to_string(),
+            destination: "c".to_string(),
+            cost: path.as_ref().map(|_path| 3),
+            path: path.map(|path| path.iter().map(|node| node.to_string()).collect()),
+            expanded: 4,
+            elapsed: Duration::from_micros(12),
+            named,
+            labels: None,
+        }
+    }
+
+    #[test]
+    fn test_search_report() {
+        let named = report(Some(vec!["a,\"b\"", "c"]), true);
+        assert_eq!(named.render(Format::Text), "Some([\"a,\\\"b\\\"\", \"c\"])");
+        assert_eq!(
+            named.render(Format::Json),
+            "{\"algorithm\":\"bfs\",\"source\":\"a,\\\"b\\\"\",\"destination\":\"c\",\"found\":true,\
+             \"path\":[\"a,\\\"b\\\"\",\"c\"],\"length\":1,\"cost\":3,\"expanded\":4,\"time_us\":12}"
+        );
+        assert_eq!(
+            named.render(Format::Csv),
+            "bfs,\"a,\"\"b\"\"\",c,true,\"a,\"\"b\"\" c\",1,3,4,12"
+        );
+
+        let missing = report(None, false);
+        assert_eq!(missing.render(Format::Text), "None");
+        assert!(missing
+            .render(Format::Json)
+            .contains("\"path\":null,\"length\":null"));
+        assert_eq!(
+            missing.render(Format::Csv),
+            "bfs,\"a,\"\"b\"\"\",c,false,,,,4,12"
+        );
+    }
+
+    #[test]
+    fn test_render_query() {
+        let named = report(Some(vec!["a,\"b\"", "c"]), true);
+        assert_eq!(
+            named.render_query(Format::Text),
+            "bfs \"a,\\\"b\\\"\" \"c\": Some([\"a,\\\"b\\\"\", \"c\"])"
+        );
+        assert!(named.render_query(Format::Csv).ends_with(",4,12,"));
+        assert_eq!(
+            render_query_error(3, &["bfs", "x"], "expected 3 fields", Format::Text),
+            "line 3: error: expected 3 fields"
+        );
+        assert_eq!(
+            render_query_error(3, &["bfs", "x"], "expected 3 fields", Format::Json),
+            "{\"line\":3,\"error\":\"expected 3 fields\"}"
+        );
+        assert_eq!(
+            render_query_error(3, &["bfs", "x"], "expected 3 fields", Format::Csv),
+            "bfs,x,,false,,,,,,line 3: expected 3 fields"
+        );
+    }
+}
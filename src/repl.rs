@@ -0,0 +1,258 @@
+// An interactive shell over the library, one command per line
+use super::{load_graph, search, LoadedGraph};
+use crate::output::Format;
+use algorithms::data::data::graph::EdgeWeight;
+use algorithms::data::data::stack::Stack;
+use algorithms::data::data::trie::Trie;
+use algorithms::data::data::{hash_set, hash_set2};
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+load <file>                          load a native or named graph file
+add-edge <from> <to> [weight]        add an edge, or change its weight
+remove-edge <from> <to>              remove an edge
+bfs|dfs|dijkstra <from> <to>         search the graph and print the path
+push <item> | pop | peek             use the stack
+set add|contains|remove <item>       use the open addressing hash set
+set2 add|contains|remove <item>      use the separate chaining hash set
+trie add|contains|prefix|remove <word>
+help
+quit";
+
+pub struct Repl {
+    graph: Option<LoadedGraph>,
+    stack: Stack<String>,
+    set: hash_set::HashSet<String>,
+    set2: hash_set2::HashSet<String>,
+    trie: Trie,
+    format: Format,
+}
+
+fn yes_no(answer: bool) -> String {
+    match answer {
+        true => "yes".to_string(),
+        false => "no".to_string(),
+    }
+}
+
+impl Repl {
+    pub fn new(format: Format) -> Repl {
+        Repl {
+            graph: None,
+            stack: Stack::new(),
+            set: hash_set::HashSet::new(),
+            set2: hash_set2::HashSet::new(),
+            trie: Trie::new(),
+            format,
+        }
+    }
+
+    // What to print for one line of input. Mistakes come back as `error: ...`.
+    pub fn execute(&mut self, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match self.run(&words) {
+            Ok(output) => output,
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    fn graph(&mut self) -> Result<&mut LoadedGraph, String> {
+        self.graph
+            .as_mut()
+            .ok_or_else(|| "no graph loaded, try `load <file>`".to_string())
+    }
+
+    fn run(&mut self, words: &[&str]) -> Result<String, String> {
+        match words {
+            [] => Ok(String::new()),
+            ["help"] => Ok(HELP.to_string()),
+            ["load", input] => {
+                let loaded = load_graph(input)?;
+                let summary = format!(
+                    "loaded {} nodes and {} edges",
+                    loaded.graph.graph().num_nodes(),
+                    loaded.graph.graph().num_edges()
+                );
+                self.graph = Some(loaded);
+                Ok(summary)
+            }
+            ["add-edge", from, to, weight @ ..] => {
+                let weight: EdgeWeight = match weight {
+                    [] => 1,
+                    [weight] => weight
+                        .parse()
+                        .map_err(|_| format!("{:?} is not a valid weight", weight))?,
+                    _ => return Err("usage: add-edge <from> <to> [weight]".to_string()),
+                };
+                let graph = self.graph()?;
+                graph.label_or_add(from)?;
+                graph.label_or_add(to)?;
+                graph
+                    .graph
+                    .add_edge(from.to_string(), to.to_string(), weight);
+                Ok(String::new())
+            }
+            ["remove-edge", from, to] => {
+                let graph = self.graph()?;
+                graph.label(from)?;
+                graph.label(to)?;
+                match graph.graph.remove_edge(*from, *to) {
+                    Some(_weight) => Ok(String::new()),
+                    None => Err("there is no such edge".to_string()),
+                }
+            }
+            [algorithm @ "bfs", from, to]
+            | [algorithm @ "dfs", from, to]
+            | [algorithm @ "dijkstra", from, to] => {
+                let format = self.format;
+                let graph = self.graph()?;
+                let (from, to) = (graph.label(from)?, graph.label(to)?);
+                Ok(search(graph, algorithm, from, to)?.render(format))
+            }
+            ["push", item] => {
+                self.stack.push(item.to_string());
+                Ok(String::new())
+            }
+            ["pop"] => self
+                .stack
+                .pop()
+                .cloned()
+                .ok_or_else(|| "the stack is empty".to_string()),
+            ["peek"] => self
+                .stack
+                .peek()
+                .cloned()
+                .ok_or_else(|| "the stack is empty".to_string()),
+            ["set", operation, item] => {
+                let item = item.to_string();
+                match *operation {
+                    "add" => Ok(yes_no(self.set.add(item))),
+                    "contains" => Ok(yes_no(self.set.contains(&item))),
+                    "remove" => Ok(yes_no(self.set.remove(&item))),
+                    _ => Err("usage: set add|contains|remove <item>".to_string()),
+                }
+            }
+            ["set2", operation, item] => {
+                let item = item.to_string();
+                match *operation {
+                    "add" => Ok(yes_no(self.set2.add(item))),
+                    "contains" => Ok(yes_no(self.set2.contains(&item))),
+                    "remove" => Ok(yes_no(self.set2.remove(&item))),
+                    _ => Err("usage: set2 add|contains|remove <item>".to_string()),
+                }
+            }
+            ["trie", operation, word] => match *operation {
+                "add" => {
+                    self.trie.add(word);
+                    Ok(String::new())
+                }
+                "contains" => Ok(yes_no(self.trie.contains(word))),
+                "prefix" => Ok(yes_no(self.trie.contains_prefix(word))),
+                "remove" => Ok(yes_no(self.trie.remove(word))),
+                _ => Err("usage: trie add|contains|prefix|remove <word>".to_string()),
+            },
+            [command, ..] => Err(format!("unknown command {:?}, try `help`", command)),
+        }
+    }
+}
+
+// Reads commands until quit or the end of input
+pub fn run<R: BufRead, W: Write>(repl: &mut Repl, input: R, mut output: W) -> io::Result<()> {
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if matches!(line.trim(), "quit" | "exit") {
+            break;
+        }
+        let result = repl.execute(&line);
+        if !result.is_empty() {
+            writeln!(output, "{}", result)?;
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    writeln!(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(commands: &[&str]) -> Vec<String> {
+        let mut repl = Repl::new(Format::Text);
+        commands
+            .iter()
+            .map(|command| repl.execute(command))
+            .collect()
+    }
+
+    #[test]
+    fn test_graph_commands() {
+        let outputs = session(&[
+            "bfs 0 4",
+            "load tests/graph_search_1.txt",
+            "bfs 0 4",
+            "dfs 0 4",
+            "remove-edge 2 4",
+            "bfs 0 4",
+            "add-edge 0 4 7",
+            "dijkstra 0 4",
+            "bfs 0 10",
+        ]);
+        assert_eq!(outputs[0], "error: no graph loaded, try `load <file>`");
+        assert_eq!(outputs[1], "loaded 10 nodes and 5 edges");
+        assert_eq!(outputs[2], "Some([0, 1, 2, 4])");
+        assert_eq!(outputs[3], "Some([0, 1, 2, 3, 4])");
+        assert_eq!(outputs[5], "Some([0, 1, 2, 3, 4])");
+        assert_eq!(outputs[7], "Some([0, 1, 2, 3, 4])");
+        assert_eq!(outputs[8], "error: there is no node \"10\"");
+    }
+
+    #[test]
+    fn test_data_structure_commands() {
+        let outputs = session(&[
+            "push a",
+            "push b",
+            "pop",
+            "peek",
+            "pop",
+            "pop",
+            "set add x",
+            "set add x",
+            "set contains x",
+            "set remove x",
+            "set contains x",
+            "set2 add y",
+            "set2 remove z",
+            "trie add hello",
+            "trie prefix hel",
+            "trie contains hel",
+            "trie remove hello",
+            "trie prefix h",
+            "frobnicate",
+        ]);
+        let expected = [
+            "",
+            "",
+            "b",
+            "a",
+            "a",
+            "error: the stack is empty",
+            "yes",
+            "no",
+            "yes",
+            "yes",
+            "no",
+            "yes",
+            "no",
+            "",
+            "yes",
+            "no",
+            "yes",
+            "no",
+            "error: unknown command \"frobnicate\", try `help`",
+        ];
+        assert_eq!(outputs, expected);
+    }
+}